@@ -0,0 +1,152 @@
+use crate::attr::With;
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{parse_quote, DataEnum, Type};
+
+/// Whether the type is a fixed-size array, which miniserde only implements its
+/// traits for in recent versions.
+fn is_array(ty: &Type) -> bool {
+    match ty {
+        Type::Array(_) => true,
+        Type::Group(group) => is_array(&group.elem),
+        Type::Paren(paren) => is_array(&paren.elem),
+        _ => false,
+    }
+}
+
+/// Use the generated array functions for a field of array type that does not
+/// set its own.
+pub fn default_with(ty: &Type, with: &mut With) {
+    private!(__deserialize_array, __serialize_array);
+    if is_array(ty) {
+        with.serialize
+            .get_or_insert_with(|| parse_quote!(#__serialize_array));
+        with.deserialize
+            .get_or_insert_with(|| parse_quote!(#__deserialize_array));
+    }
+}
+
+fn has_array_field(enumeration: &DataEnum) -> bool {
+    enumeration
+        .variants
+        .iter()
+        .flat_map(|variant| &variant.fields)
+        .any(|field| is_array(&field.ty))
+}
+
+/// The function serializing array fields, if the enum has any.
+pub fn serialize_helper(enumeration: &DataEnum) -> TokenStream {
+    private!(__alloc, __ArraySeq, __miniserde, __serialize_array, __value; __b);
+    if !has_array_field(enumeration) {
+        return quote!();
+    }
+    quote! {
+        #[allow(dead_code)]
+        fn #__serialize_array<__T, const __N: usize>(
+            #__value: &[__T; __N],
+        ) -> #__miniserde::ser::Fragment
+        where
+            __T: #__miniserde::Serialize,
+        {
+            struct #__ArraySeq<#__b, __T> {
+                iter: ::core::slice::Iter<#__b, __T>,
+            }
+
+            #[automatically_derived]
+            impl<#__b, __T: #__miniserde::Serialize> #__miniserde::ser::Seq for #__ArraySeq<#__b, __T> {
+                fn next(&mut self) -> ::core::option::Option<&dyn #__miniserde::Serialize> {
+                    let #__value = self.iter.next()?;
+                    ::core::option::Option::Some(#__value)
+                }
+            }
+
+            #__miniserde::ser::Fragment::Seq(#__alloc::boxed::Box::new(#__ArraySeq {
+                iter: #__value.iter(),
+            }))
+        }
+    }
+}
+
+/// The function deserializing array fields, if the enum has any. The elements
+/// are collected into a vector, then converted to an array of the right length.
+pub fn deserialize_helper(enumeration: &DataEnum) -> TokenStream {
+    private!(
+        __alloc,
+        __array,
+        __ArraySeq,
+        __ArrayVisitor,
+        __deserialize_array,
+        __elements,
+        __miniserde,
+        __out;
+        __a
+    );
+    if !has_array_field(enumeration) {
+        return quote!();
+    }
+    quote! {
+        #[allow(dead_code)]
+        fn #__deserialize_array<__T, const __N: usize>(
+            #__out: &mut ::core::option::Option<[__T; __N]>,
+        ) -> &mut dyn #__miniserde::de::Visitor
+        where
+            __T: #__miniserde::Deserialize,
+        {
+            #[repr(C)]
+            struct #__ArrayVisitor<__T, const __N: usize> {
+                #__out: ::core::option::Option<[__T; __N]>,
+            }
+
+            #[automatically_derived]
+            impl<__T, const __N: usize> #__miniserde::de::Visitor for #__ArrayVisitor<__T, __N>
+            where
+                __T: #__miniserde::Deserialize,
+            {
+                fn seq(&mut self) -> #__miniserde::Result<#__alloc::boxed::Box<dyn #__miniserde::de::Seq + '_>> {
+                    ::core::result::Result::Ok(#__alloc::boxed::Box::new(#__ArraySeq {
+                        elements: #__alloc::vec::Vec::with_capacity(__N),
+                        element: ::core::option::Option::None,
+                        #__out: &mut self.#__out,
+                    }))
+                }
+            }
+
+            struct #__ArraySeq<#__a, __T, const __N: usize> {
+                elements: #__alloc::vec::Vec<__T>,
+                element: ::core::option::Option<__T>,
+                #__out: &#__a mut ::core::option::Option<[__T; __N]>,
+            }
+
+            #[automatically_derived]
+            impl<#__a, __T, const __N: usize> #__miniserde::de::Seq for #__ArraySeq<#__a, __T, __N>
+            where
+                __T: #__miniserde::Deserialize,
+            {
+                fn element(&mut self) -> #__miniserde::Result<&mut dyn #__miniserde::de::Visitor> {
+                    self.elements.extend(self.element.take());
+                    if self.elements.len() == __N {
+                        return ::core::result::Result::Err(#__miniserde::Error);
+                    }
+                    ::core::result::Result::Ok(<__T as #__miniserde::Deserialize>::begin(&mut self.element))
+                }
+
+                fn finish(&mut self) -> #__miniserde::Result<()> {
+                    self.elements.extend(self.element.take());
+                    let #__elements = ::core::mem::take(&mut self.elements);
+                    let #__array = <[__T; __N] as ::core::convert::TryFrom<_>>::try_from(#__elements)
+                        .map_err(|_| #__miniserde::Error)?;
+                    *self.#__out = ::core::option::Option::Some(#__array);
+                    ::core::result::Result::Ok(())
+                }
+            }
+
+            unsafe {
+                &mut *{
+                    #__out
+                        as *mut ::core::option::Option<[__T; __N]>
+                        as *mut #__ArrayVisitor<__T, __N>
+                }
+            }
+        }
+    }
+}
@@ -1,10 +1,65 @@
 use crate::TagType;
-use syn::{Attribute, DataEnum, Error, Field, Fields, Lit, Meta, NestedMeta, Result, Variant};
+use syn::ext::IdentExt;
+use syn::punctuated::Punctuated;
+use syn::{
+    parse_quote, Attribute, DataEnum, Error, ExprPath, Field, Fields, Ident, Lit, Meta,
+    MetaNameValue, NestedMeta, Path, PathArguments, Result, Token, Type, Variant, WherePredicate,
+};
 
-pub(crate) fn tag_type(attrs: &[Attribute], enumeration: &DataEnum) -> Result<TagType> {
+/// The serde attributes of the enum itself.
+pub struct ContainerAttrs {
+    pub tag_type: TagType,
+    /// Whether keys that belong neither to the representation of the enum nor
+    /// to a field are rejected, instead of being ignored.
+    pub deny_unknown_fields: bool,
+    /// Whether a key that appears twice in a map is rejected, instead of the
+    /// later value replacing the earlier one.
+    pub deny_duplicate_keys: bool,
+    /// A type the enum is deserialized as before being converted with `From`.
+    pub from: Option<Type>,
+    /// A type the enum is deserialized as before being converted with `TryFrom`.
+    pub try_from: Option<Type>,
+    /// A type a clone of the enum is converted into with `Into` to be serialized.
+    pub into: Option<Type>,
+    /// The enum from another crate that the derived enum mirrors.
+    pub remote: Option<Path>,
+    pub bound: Bound,
+    /// The path miniserde is reachable through, if not `::miniserde`.
+    pub crate_path: Option<Path>,
+}
+
+impl ContainerAttrs {
+    /// The path the generated code names variants with: the remote enum if
+    /// there is one, without its generic arguments.
+    pub fn enum_path(&self, ident: &Ident) -> Path {
+        match &self.remote {
+            Some(remote) => {
+                let mut path = remote.clone();
+                if let Some(last) = path.segments.last_mut() {
+                    last.arguments = PathArguments::None;
+                }
+                path
+            }
+            None => ident.clone().into(),
+        }
+    }
+}
+
+pub(crate) fn container_attrs(
+    attrs: &[Attribute],
+    enumeration: &DataEnum,
+) -> Result<ContainerAttrs> {
     let mut tag_type = None;
     let mut tag = None;
     let mut content = None;
+    let mut deny_unknown_fields = None;
+    let mut deny_duplicate_keys = false;
+    let mut from = None;
+    let mut try_from = None;
+    let mut into = None;
+    let mut remote = None;
+    let mut bound = Bound::default();
+    let mut crate_path = None;
 
     for attr in attrs {
         if !attr.path.is_ident("serde") {
@@ -17,6 +72,9 @@ pub(crate) fn tag_type(attrs: &[Attribute], enumeration: &DataEnum) -> Result<Ta
         };
 
         for meta in &list.nested {
+            if bound.parse(meta)? {
+                continue;
+            }
             match meta {
                 NestedMeta::Meta(Meta::NameValue(value)) => {
                     if value.path.is_ident("tag") {
@@ -38,6 +96,31 @@ pub(crate) fn tag_type(attrs: &[Attribute], enumeration: &DataEnum) -> Result<Ta
                             content = Some(s.value());
                             continue;
                         }
+                    } else if value.path.is_ident("from") {
+                        if let Lit::Str(s) = &value.lit {
+                            set_once(&mut from, s.parse()?, meta, "from")?;
+                            continue;
+                        }
+                    } else if value.path.is_ident("try_from") {
+                        if let Lit::Str(s) = &value.lit {
+                            set_once(&mut try_from, s.parse()?, meta, "try_from")?;
+                            continue;
+                        }
+                    } else if value.path.is_ident("into") {
+                        if let Lit::Str(s) = &value.lit {
+                            set_once(&mut into, s.parse()?, meta, "into")?;
+                            continue;
+                        }
+                    } else if value.path.is_ident("crate") {
+                        if let Lit::Str(s) = &value.lit {
+                            set_once(&mut crate_path, s.parse()?, meta, "crate")?;
+                            continue;
+                        }
+                    } else if value.path.is_ident("remote") {
+                        if let Lit::Str(s) = &value.lit {
+                            set_once(&mut remote, s.parse()?, meta, "remote")?;
+                            continue;
+                        }
                     }
                 }
                 NestedMeta::Meta(Meta::Path(path)) => {
@@ -48,20 +131,65 @@ pub(crate) fn tag_type(attrs: &[Attribute], enumeration: &DataEnum) -> Result<Ta
                         tag_type = Some(TagType::Untagged);
                         continue;
                     }
+                    if path.is_ident("deny_unknown_fields") || path.is_ident("allow_unknown_fields")
+                    {
+                        let deny = path.is_ident("deny_unknown_fields");
+                        set_once(&mut deny_unknown_fields, deny, meta, "unknown fields")?;
+                        continue;
+                    }
+                    if path.is_ident("deny_duplicate_keys") {
+                        if deny_duplicate_keys {
+                            return Err(Error::new_spanned(
+                                meta,
+                                "duplicate deny_duplicate_keys attribute",
+                            ));
+                        }
+                        deny_duplicate_keys = true;
+                        continue;
+                    }
                 }
                 _ => (),
             }
             return Err(Error::new_spanned(meta, "unsupported attribute"));
         }
     }
-    if let Some(ty) = tag_type {
-        return Ok(ty);
+    if let (Some(_), Some(try_from)) = (&from, &try_from) {
+        return Err(Error::new_spanned(
+            try_from,
+            "from and try_from cannot be used together",
+        ));
     }
+    Ok(ContainerAttrs {
+        tag_type: match tag_type {
+            Some(ty) => ty,
+            None => tag_type_of(attrs, enumeration, tag, content)?,
+        },
+        deny_unknown_fields: deny_unknown_fields.unwrap_or(false),
+        deny_duplicate_keys,
+        from,
+        try_from,
+        into,
+        remote,
+        bound,
+        crate_path,
+    })
+}
 
+fn tag_type_of(
+    attrs: &[Attribute],
+    enumeration: &DataEnum,
+    tag: Option<String>,
+    content: Option<String>,
+) -> Result<TagType> {
     match (tag, content) {
         (None, None) => Ok(TagType::External),
         (Some(tag), None) => {
-            for fields in enumeration.variants.iter().map(|v| &v.fields) {
+            for variant in &enumeration.variants {
+                let attrs = variant_attrs(variant)?;
+                if attrs.skip_serializing.is_some() && attrs.skip_deserializing {
+                    continue;
+                }
+                let fields = &variant.fields;
                 if let Fields::Unnamed(_) = fields {
                     return Err(Error::new_spanned(
                         fields,
@@ -79,47 +207,383 @@ pub(crate) fn tag_type(attrs: &[Attribute], enumeration: &DataEnum) -> Result<Ta
     }
 }
 
-/// Find the value of a #[serde(rename = "...")] attribute.
-fn attr_rename(attrs: &[Attribute]) -> Result<Option<String>> {
-    let mut rename = None;
+/// The attributes of a variant that also apply to the helper struct generated
+/// for it: cfg and doc. The others, such as serde's own and those of other
+/// derives and tools, are handled here or mean nothing to the helper.
+pub fn forwarded_attrs(attrs: &[Attribute]) -> impl Iterator<Item = &Attribute> {
+    attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident("cfg") || attr.path.is_ident("doc"))
+}
+
+/// Collect the items of every #[serde(...)] attribute.
+fn serde_meta_items(attrs: &[Attribute]) -> Result<Vec<NestedMeta>> {
+    let mut items = Vec::new();
 
     for attr in attrs {
         if !attr.path.is_ident("serde") {
             continue;
         }
 
-        let list = match attr.parse_meta()? {
-            Meta::List(list) => list,
+        match attr.parse_meta()? {
+            Meta::List(list) => items.extend(list.nested),
             other => return Err(Error::new_spanned(other, "unsupported attribute")),
-        };
+        }
+    }
 
-        for meta in &list.nested {
-            if let NestedMeta::Meta(Meta::NameValue(value)) = meta {
-                if value.path.is_ident("rename") {
-                    if let Lit::Str(s) = &value.lit {
-                        if rename.is_some() {
-                            return Err(Error::new_spanned(meta, "duplicate rename attribute"));
+    Ok(items)
+}
+
+/// Store the value of an attribute, rejecting duplicates.
+fn set_once<T>(slot: &mut Option<T>, value: T, meta: &NestedMeta, name: &str) -> Result<()> {
+    if slot.is_some() {
+        let message = format!("duplicate {} attribute", name);
+        return Err(Error::new_spanned(meta, message));
+    }
+    *slot = Some(value);
+    Ok(())
+}
+
+/// Where predicates replacing the bounds inferred for the generated impls, set
+/// with bound = "..." or bound(serialize = "...", deserialize = "...").
+#[derive(Default)]
+pub struct Bound {
+    pub serialize: Option<Vec<WherePredicate>>,
+    pub deserialize: Option<Vec<WherePredicate>>,
+}
+
+impl Bound {
+    /// Parse a bound item, returning false if the item is not one.
+    fn parse(&mut self, meta: &NestedMeta) -> Result<bool> {
+        match meta {
+            NestedMeta::Meta(Meta::NameValue(value)) if value.path.is_ident("bound") => {
+                let predicates = parse_predicates(&value.lit)?;
+                set_once(&mut self.serialize, predicates.clone(), meta, "bound")?;
+                set_once(&mut self.deserialize, predicates, meta, "bound")?;
+            }
+            NestedMeta::Meta(Meta::List(list)) if list.path.is_ident("bound") => {
+                for item in &list.nested {
+                    match item {
+                        NestedMeta::Meta(Meta::NameValue(value))
+                            if value.path.is_ident("serialize") =>
+                        {
+                            let predicates = parse_predicates(&value.lit)?;
+                            set_once(&mut self.serialize, predicates, item, "bound")?;
                         }
-                        rename = Some(s.value());
-                        continue;
+                        NestedMeta::Meta(Meta::NameValue(value))
+                            if value.path.is_ident("deserialize") =>
+                        {
+                            let predicates = parse_predicates(&value.lit)?;
+                            set_once(&mut self.deserialize, predicates, item, "bound")?;
+                        }
+                        _ => return Err(Error::new_spanned(item, "unsupported attribute")),
                     }
                 }
             }
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+}
+
+/// Parse a string of comma separated where predicates.
+fn parse_predicates(lit: &Lit) -> Result<Vec<WherePredicate>> {
+    match lit {
+        Lit::Str(s) => {
+            let predicates =
+                s.parse_with(Punctuated::<WherePredicate, Token![,]>::parse_terminated)?;
+            Ok(predicates.into_iter().collect())
+        }
+        _ => Err(Error::new_spanned(lit, "unsupported attribute")),
+    }
+}
+
+/// Functions replacing the Serialize and Deserialize impls of a value, set with
+/// with, serialize_with or deserialize_with.
+///
+/// A serialize function has the signature `fn(&T) -> miniserde::ser::Fragment`
+/// and a deserialize function the signature
+/// `fn(&mut Option<T>) -> &mut dyn miniserde::de::Visitor`. `with = "module"`
+/// uses `module::serialize` and `module::deserialize`.
+#[derive(Clone, Default)]
+pub struct With {
+    pub serialize: Option<ExprPath>,
+    pub deserialize: Option<ExprPath>,
+}
+
+impl With {
+    fn is_empty(&self) -> bool {
+        self.serialize.is_none() && self.deserialize.is_none()
+    }
+
+    /// Parse a with, serialize_with or deserialize_with item, returning false
+    /// if the item is none of these.
+    fn parse(&mut self, meta: &NestedMeta, value: &MetaNameValue) -> Result<bool> {
+        let s = match &value.lit {
+            Lit::Str(s) if value.path.is_ident("with") => {
+                let module = s.parse::<ExprPath>()?;
+                let serialize = parse_quote!(#module::serialize);
+                let deserialize = parse_quote!(#module::deserialize);
+                set_once(&mut self.serialize, serialize, meta, "serialize_with")?;
+                set_once(&mut self.deserialize, deserialize, meta, "deserialize_with")?;
+                return Ok(true);
+            }
+            Lit::Str(s) => s,
+            _ => return Ok(false),
+        };
+        if value.path.is_ident("serialize_with") {
+            set_once(&mut self.serialize, s.parse()?, meta, "serialize_with")?;
+        } else if value.path.is_ident("deserialize_with") {
+            set_once(&mut self.deserialize, s.parse()?, meta, "deserialize_with")?;
+        } else {
+            return Ok(false);
+        }
+        Ok(true)
+    }
+}
+
+/// Parse the attributes of every element of a tuple variant. Only with,
+/// serialize_with and deserialize_with are supported, and may also be set on a
+/// newtype variant itself.
+pub fn element_with(var: &Variant) -> Result<Vec<With>> {
+    let variant_with = variant_attrs(var)?.with;
+    let mut elements = Vec::new();
+
+    for field in &var.fields {
+        let mut with = With::default();
+        for meta in &serde_meta_items(&field.attrs)? {
+            if let NestedMeta::Meta(Meta::NameValue(value)) = meta {
+                if with.parse(meta, value)? {
+                    continue;
+                }
+            }
             return Err(Error::new_spanned(meta, "unsupported attribute"));
         }
+        elements.push(with);
+    }
+
+    if !variant_with.is_empty() {
+        if !elements[0].is_empty() {
+            return Err(Error::new_spanned(
+                &var.fields,
+                "with attributes set on both the variant and its field",
+            ));
+        }
+        elements[0] = variant_with;
     }
 
-    Ok(rename)
+    Ok(elements)
 }
 
-/// Determine the name of a field, respecting a rename attribute.
-pub fn name_of_field(field: &Field) -> Result<String> {
-    let rename = attr_rename(&field.attrs)?;
-    Ok(rename.unwrap_or_else(|| field.ident.as_ref().unwrap().to_string()))
+/// Where the value of a field missing from the input comes from.
+#[derive(Clone)]
+pub enum DefaultValue {
+    /// `Default::default()`.
+    Trait,
+    /// A call to the given function.
+    Path(ExprPath),
 }
 
-/// Determine the name of a variant, respecting a rename attribute.
-pub fn name_of_variant(var: &Variant) -> Result<String> {
-    let rename = attr_rename(&var.attrs)?;
-    Ok(rename.unwrap_or_else(|| var.ident.to_string()))
+/// The serde attributes of a field of a struct variant.
+pub struct FieldAttrs {
+    pub name: String,
+    pub skip_serializing: bool,
+    pub skip_deserializing: bool,
+    pub default: Option<DefaultValue>,
+    pub skip_serializing_if: Option<ExprPath>,
+    pub with: With,
+    pub flatten: bool,
+}
+
+/// Parse the attributes of a field: rename, skip, skip_serializing,
+/// skip_deserializing, default, skip_serializing_if, flatten and the with
+/// family.
+pub fn field_attrs(field: &Field) -> Result<FieldAttrs> {
+    let mut rename = None;
+    let mut skip_serializing = false;
+    let mut skip_deserializing = false;
+    let mut default = None;
+    let mut skip_serializing_if = None;
+    let mut with = With::default();
+    let mut flatten = None;
+
+    for meta in &serde_meta_items(&field.attrs)? {
+        match meta {
+            NestedMeta::Meta(Meta::NameValue(value)) => {
+                if with.parse(meta, value)? {
+                    continue;
+                }
+                let s = match &value.lit {
+                    Lit::Str(s) => s,
+                    _ => return Err(Error::new_spanned(meta, "unsupported attribute")),
+                };
+                if value.path.is_ident("rename") {
+                    set_once(&mut rename, s.value(), meta, "rename")?;
+                } else if value.path.is_ident("default") {
+                    let path = DefaultValue::Path(s.parse()?);
+                    set_once(&mut default, path, meta, "default")?;
+                } else if value.path.is_ident("skip_serializing_if") {
+                    let path = s.parse()?;
+                    set_once(&mut skip_serializing_if, path, meta, "skip_serializing_if")?;
+                } else {
+                    return Err(Error::new_spanned(meta, "unsupported attribute"));
+                }
+            }
+            NestedMeta::Meta(Meta::Path(path)) => {
+                if path.is_ident("default") {
+                    set_once(&mut default, DefaultValue::Trait, meta, "default")?;
+                    continue;
+                }
+                if path.is_ident("flatten") {
+                    set_once(&mut flatten, meta.clone(), meta, "flatten")?;
+                    continue;
+                }
+                let (ser, de) = if path.is_ident("skip") {
+                    (true, true)
+                } else if path.is_ident("skip_serializing") {
+                    (true, false)
+                } else if path.is_ident("skip_deserializing") {
+                    (false, true)
+                } else {
+                    return Err(Error::new_spanned(meta, "unsupported attribute"));
+                };
+                if (ser && skip_serializing) || (de && skip_deserializing) {
+                    return Err(Error::new_spanned(meta, "duplicate skip attribute"));
+                }
+                skip_serializing |= ser;
+                skip_deserializing |= de;
+            }
+            _ => return Err(Error::new_spanned(meta, "unsupported attribute")),
+        }
+    }
+
+    // A flattened field has no key of its own, and its entries are serialized
+    // through its Serialize impl.
+    if let Some(meta) = &flatten {
+        let conflict = if rename.is_some() {
+            Some("rename")
+        } else if skip_serializing_if.is_some() {
+            Some("skip_serializing_if")
+        } else if with.serialize.is_some() {
+            Some("serialize_with")
+        } else {
+            None
+        };
+        if let Some(conflict) = conflict {
+            let message = format!("flatten cannot be combined with {}", conflict);
+            return Err(Error::new_spanned(meta, message));
+        }
+    }
+
+    Ok(FieldAttrs {
+        name: rename.unwrap_or_else(|| field.ident.as_ref().unwrap().unraw().to_string()),
+        skip_serializing,
+        skip_deserializing,
+        default,
+        skip_serializing_if,
+        with,
+        flatten: flatten.is_some(),
+    })
+}
+
+/// What to emit when serializing a variant marked with skip or skip_serializing.
+pub enum SkipSerializing {
+    /// Panic, the variant must never be serialized.
+    Fail,
+    /// Serialize the variant as if it were a unit variant with this name.
+    Placeholder(String),
+}
+
+/// The serde attributes of a variant.
+pub struct VariantAttrs {
+    pub name: String,
+    pub skip_serializing: Option<SkipSerializing>,
+    pub skip_deserializing: bool,
+    /// Whether every field missing from the input takes its default value. Only
+    /// allowed on struct variants.
+    pub default: bool,
+    /// Only allowed on newtype variants, see `element_with`.
+    pub with: With,
+    pub bound: Bound,
+}
+
+/// Parse the attributes of a variant: rename, skip, skip_serializing,
+/// skip_deserializing, default, bound and the with family.
+pub fn variant_attrs(var: &Variant) -> Result<VariantAttrs> {
+    let mut rename = None;
+    let mut skip_serializing = None;
+    let mut skip_deserializing = false;
+    let mut default = false;
+    let mut with = With::default();
+    let mut bound = Bound::default();
+
+    for meta in &serde_meta_items(&var.attrs)? {
+        if bound.parse(meta)? {
+            continue;
+        }
+        let (path, placeholder) = match meta {
+            NestedMeta::Meta(Meta::NameValue(value)) if with.parse(meta, value)? => {
+                match &var.fields {
+                    Fields::Unnamed(fields) if fields.unnamed.len() == 1 => continue,
+                    _ => {
+                        return Err(Error::new_spanned(
+                            meta,
+                            "with attributes are only supported on newtype variants",
+                        ))
+                    }
+                }
+            }
+            NestedMeta::Meta(Meta::NameValue(value)) => match &value.lit {
+                Lit::Str(s) if value.path.is_ident("rename") => {
+                    set_once(&mut rename, s.value(), meta, "rename")?;
+                    continue;
+                }
+                Lit::Str(s) => (&value.path, Some(s.value())),
+                _ => return Err(Error::new_spanned(meta, "unsupported attribute")),
+            },
+            NestedMeta::Meta(Meta::Path(path)) if path.is_ident("default") => {
+                if !matches!(var.fields, Fields::Named(_)) {
+                    return Err(Error::new_spanned(
+                        path,
+                        "default is only supported on struct variants",
+                    ));
+                }
+                if default {
+                    return Err(Error::new_spanned(meta, "duplicate default attribute"));
+                }
+                default = true;
+                continue;
+            }
+            NestedMeta::Meta(Meta::Path(path)) => (path, None),
+            _ => return Err(Error::new_spanned(meta, "unsupported attribute")),
+        };
+        let (ser, de) = if path.is_ident("skip") {
+            (true, true)
+        } else if path.is_ident("skip_serializing") {
+            (true, false)
+        } else if path.is_ident("skip_deserializing") && placeholder.is_none() {
+            (false, true)
+        } else {
+            return Err(Error::new_spanned(meta, "unsupported attribute"));
+        };
+        if (ser && skip_serializing.is_some()) || (de && skip_deserializing) {
+            return Err(Error::new_spanned(meta, "duplicate skip attribute"));
+        }
+        if ser {
+            skip_serializing = Some(match placeholder {
+                Some(name) => SkipSerializing::Placeholder(name),
+                None => SkipSerializing::Fail,
+            });
+        }
+        skip_deserializing |= de;
+    }
+
+    Ok(VariantAttrs {
+        name: rename.unwrap_or_else(|| var.ident.unraw().to_string()),
+        skip_serializing,
+        skip_deserializing,
+        default,
+        with,
+        bound,
+    })
 }
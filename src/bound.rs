@@ -1,12 +1,13 @@
-use proc_macro2::{Span, TokenStream};
+use crate::attr::{self, Bound};
+use proc_macro2::TokenStream;
+use quote::{quote, ToTokens};
 use syn::punctuated::Punctuated;
 use syn::{
-    parse_quote, GenericParam, Generics, Lifetime, LifetimeDef, TypeParamBound, WhereClause,
-    WherePredicate,
+    parse_quote, DataEnum, GenericArgument, GenericParam, Generics, Ident, Lifetime, LifetimeDef,
+    PathArguments, Result, ReturnType, Type, TypeParamBound, Variant, WhereClause, WherePredicate,
 };
 
-pub fn with_lifetime_bound(generics: &Generics, lifetime: &str) -> Generics {
-    let bound = Lifetime::new(lifetime, Span::call_site());
+pub fn with_lifetime_bound(generics: &Generics, bound: &Lifetime) -> Generics {
     let def = LifetimeDef {
         attrs: Vec::new(),
         lifetime: bound.clone(),
@@ -31,21 +32,150 @@ pub fn with_lifetime_bound(generics: &Generics, lifetime: &str) -> Generics {
         .collect();
 
     Generics {
-        params: params,
+        params,
         ..generics.clone()
     }
 }
 
-pub fn where_clause_with_bound(generics: &Generics, bound: TokenStream) -> WhereClause {
-    let new_predicates = generics.type_params().map::<WherePredicate, _>(|param| {
-        let param = &param.ident;
-        parse_quote!(#param : #bound)
-    });
+/// Yields the types of the fields of a variant that need a bound, along with the
+/// trait they must implement.
+pub type FieldTypes = fn(&Variant) -> Result<Vec<(&Type, TokenStream)>>;
 
+/// The where clause of the impls generated for an enum: its own predicates, plus
+/// those of the bound attribute of the container selected by `select` or, failing
+/// that, the ones inferred from the fields of each variant. The fields yielded by
+/// `field_types` come with the trait their type must implement. A variant with a
+/// bound attribute of its own contributes its predicates instead.
+pub fn where_clause_for_enum(
+    generics: &Generics,
+    enumeration: &DataEnum,
+    container: &Bound,
+    select: fn(&Bound) -> &Option<Vec<WherePredicate>>,
+    field_types: FieldTypes,
+) -> Result<WhereClause> {
     let mut generics = generics.clone();
-    generics
-        .make_where_clause()
-        .predicates
-        .extend(new_predicates);
-    generics.where_clause.unwrap()
+    let type_params = generics
+        .type_params()
+        .map(|param| param.ident.clone())
+        .collect::<Vec<_>>();
+    let where_clause = generics.make_where_clause();
+    if let Some(predicates) = select(container) {
+        where_clause.predicates.extend(predicates.iter().cloned());
+        return Ok(where_clause.clone());
+    }
+
+    let mut inferred = Vec::new();
+    for variant in &enumeration.variants {
+        if let Some(predicates) = select(&attr::variant_attrs(variant)?.bound) {
+            where_clause.predicates.extend(predicates.iter().cloned());
+            continue;
+        }
+        for (ty, bound) in field_types(variant)? {
+            let mut bounded = Vec::new();
+            collect_bounded_types(ty, &type_params, &mut bounded);
+            for ty in bounded {
+                let predicate: WherePredicate = parse_quote!(#ty: #bound);
+                let key = predicate.to_token_stream().to_string();
+                if !inferred.contains(&key) {
+                    inferred.push(key);
+                    where_clause.predicates.push(predicate);
+                }
+            }
+        }
+    }
+    Ok(where_clause.clone())
+}
+
+/// Collect the types within `ty` that need the bound of the field: the type
+/// parameters it uses, and the associated types of type parameters such as
+/// `T::Id` in place of the parameter itself. Nothing within `PhantomData` is
+/// collected.
+fn collect_bounded_types(ty: &Type, params: &[Ident], out: &mut Vec<Type>) {
+    let is_param = |ident: &Ident| params.contains(ident);
+    match ty {
+        Type::Path(path) => {
+            if let Some(qself) = &path.qself {
+                match &*qself.ty {
+                    Type::Path(inner) if inner.qself.is_none() => {
+                        if let Some(ident) = inner.path.get_ident() {
+                            if is_param(ident) {
+                                out.push(ty.clone());
+                                return;
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+                collect_bounded_types(&qself.ty, params, out);
+            } else if path.path.leading_colon.is_none() {
+                if let Some(first) = path.path.segments.first() {
+                    if is_param(&first.ident) {
+                        out.push(ty.clone());
+                        return;
+                    }
+                }
+            }
+            if let Some(last) = path.path.segments.last() {
+                if last.ident == "PhantomData" {
+                    return;
+                }
+            }
+            for segment in &path.path.segments {
+                match &segment.arguments {
+                    PathArguments::AngleBracketed(arguments) => {
+                        for argument in &arguments.args {
+                            match argument {
+                                GenericArgument::Type(ty) => collect_bounded_types(ty, params, out),
+                                GenericArgument::Binding(binding) => {
+                                    collect_bounded_types(&binding.ty, params, out)
+                                }
+                                _ => {}
+                            }
+                        }
+                    }
+                    PathArguments::Parenthesized(arguments) => {
+                        for ty in &arguments.inputs {
+                            collect_bounded_types(ty, params, out);
+                        }
+                        if let ReturnType::Type(_, ty) = &arguments.output {
+                            collect_bounded_types(ty, params, out);
+                        }
+                    }
+                    PathArguments::None => {}
+                }
+            }
+        }
+        Type::Array(array) => collect_bounded_types(&array.elem, params, out),
+        Type::Group(group) => collect_bounded_types(&group.elem, params, out),
+        Type::Paren(paren) => collect_bounded_types(&paren.elem, params, out),
+        Type::Ptr(ptr) => collect_bounded_types(&ptr.elem, params, out),
+        Type::Reference(reference) => collect_bounded_types(&reference.elem, params, out),
+        Type::Slice(slice) => collect_bounded_types(&slice.elem, params, out),
+        Type::Tuple(tuple) => {
+            for ty in &tuple.elems {
+                collect_bounded_types(ty, params, out);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// The turbofish naming the type and const parameters of the enum, with which
+/// the generated helper types are constructed. Their parameters cannot always be
+/// inferred from their fields, which may only mention associated types.
+pub fn turbofish(generics: &Generics) -> TokenStream {
+    let params = generics
+        .params
+        .iter()
+        .filter_map(|param| match param {
+            GenericParam::Type(param) => Some(&param.ident),
+            GenericParam::Const(param) => Some(&param.ident),
+            GenericParam::Lifetime(_) => None,
+        })
+        .collect::<Vec<_>>();
+    if params.is_empty() {
+        quote!()
+    } else {
+        quote!(::<#(#params),*>)
+    }
 }
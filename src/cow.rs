@@ -0,0 +1,123 @@
+use crate::attr::With;
+use crate::de;
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{parse_quote, DataEnum, GenericArgument, Generics, PathArguments, Type};
+
+/// Whether the type is the standard `Cow`, which miniserde can serialize but
+/// not deserialize. A type of the same name from another crate, or one that
+/// does not start with a lifetime, is left to its own Deserialize impl.
+fn is_cow(ty: &Type) -> bool {
+    let path = match ty {
+        Type::Path(path) if path.qself.is_none() => &path.path,
+        Type::Group(group) => return is_cow(&group.elem),
+        Type::Paren(paren) => return is_cow(&paren.elem),
+        _ => return false,
+    };
+    let names = path
+        .segments
+        .iter()
+        .map(|segment| segment.ident.to_string())
+        .collect::<Vec<_>>();
+    let names = names.iter().map(String::as_str).collect::<Vec<_>>();
+    let std = match names.as_slice() {
+        ["Cow"] => path.leading_colon.is_none(),
+        ["std", "borrow", "Cow"] | ["alloc", "borrow", "Cow"] => true,
+        _ => false,
+    };
+    std && match &path.segments.last().unwrap().arguments {
+        PathArguments::AngleBracketed(arguments) => {
+            matches!(arguments.args.first(), Some(GenericArgument::Lifetime(_)))
+        }
+        _ => false,
+    }
+}
+
+/// Use the generated `Cow` function for a field of that type that does not set
+/// its own.
+pub fn default_with(ty: &Type, with: &mut With) {
+    private!(__deserialize_cow);
+    if is_cow(ty) {
+        with.deserialize
+            .get_or_insert_with(|| parse_quote!(#__deserialize_cow));
+    }
+}
+
+fn has_cow_field(enumeration: &DataEnum) -> bool {
+    enumeration
+        .variants
+        .iter()
+        .flat_map(|variant| &variant.fields)
+        .any(|field| is_cow(&field.ty))
+}
+
+/// The function deserializing `Cow` fields, if the enum has any. A `Cow` is
+/// deserialized as its owned form, like miniserde does for `Box`.
+pub fn deserialize_helper(enumeration: &DataEnum) -> TokenStream {
+    private!(
+        __alloc,
+        __CowVisitor,
+        __deserialize_cow,
+        __miniserde,
+        __out,
+        __owned,
+        __value;
+        __a
+    );
+    if !has_cow_field(enumeration) {
+        return quote!();
+    }
+    let owned: Type = parse_quote!(<__T as #__alloc::borrow::ToOwned>::Owned);
+    let target = quote!(#__alloc::borrow::Cow<'__c, __T>);
+    let mut generics: Generics = parse_quote!(<'__c, __T>);
+    generics.where_clause = Some(parse_quote! {
+        where
+            __T: ?::core::marker::Sized + #__alloc::borrow::ToOwned,
+    });
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let mut bounded_generics = generics.clone();
+    let bounded_where_clause = bounded_generics.make_where_clause();
+    bounded_where_clause
+        .predicates
+        .push(parse_quote!(#owned: #__miniserde::Deserialize));
+    let visitor = de::from_visitor(
+        &generics,
+        bounded_where_clause,
+        &__CowVisitor,
+        &target,
+        &owned,
+        &__owned,
+    );
+    quote! {
+        #[allow(dead_code)]
+        fn #__deserialize_cow<#__a, '__c, __T>(
+            #__out: &#__a mut ::core::option::Option<#target>,
+        ) -> &#__a mut dyn #__miniserde::de::Visitor
+        #bounded_where_clause
+        {
+            #[repr(C)]
+            struct #__CowVisitor #impl_generics #where_clause {
+                #__out: ::core::option::Option<#target>,
+            }
+
+            fn #__owned #impl_generics (
+                #__out: &mut ::core::option::Option<#target>,
+                #__value: ::core::option::Option<#owned>,
+            ) -> #__miniserde::Result<()> #where_clause {
+                let #__value = #__value.ok_or(#__miniserde::Error)?;
+                *#__out = ::core::option::Option::Some(#__alloc::borrow::Cow::Owned(#__value));
+                ::core::result::Result::Ok(())
+            }
+
+            #visitor
+
+            unsafe {
+                &mut *{
+                    #__out
+                        as *mut ::core::option::Option<#target>
+                        as *mut #__CowVisitor #ty_generics
+                }
+            }
+        }
+    }
+}
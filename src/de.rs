@@ -1,21 +1,32 @@
-use crate::attr;
+use crate::array;
+use crate::attr::{self, ContainerAttrs, DefaultValue};
 use crate::bound;
+use crate::cow;
+use crate::field::{self, VariantField};
+use crate::hygiene;
 use crate::TagType;
 use proc_macro2::{Span, TokenStream};
-use quote::quote;
+use quote::{quote, ToTokens};
+use syn::ext::IdentExt;
 use syn::{
-    parse_quote, DataEnum, DeriveInput, Error, Fields, FieldsNamed, FieldsUnnamed, Ident, Result,
-    Variant,
+    parse_quote, DataEnum, DeriveInput, Error, Fields, Generics, Ident, Path, Result, Type,
+    Variant, WhereClause,
 };
 
 pub fn derive(input: &DeriveInput, enumeration: &DataEnum) -> Result<TokenStream> {
-    let tag_type = attr::tag_type(&input.attrs, &enumeration)?;
-    match &tag_type {
-        TagType::External => deserialize_external(input, enumeration),
+    let attrs = attr::container_attrs(&input.attrs, enumeration)?;
+    if let Some(from) = &attrs.from {
+        return Ok(deserialize_from(input, &attrs, from, false));
+    }
+    if let Some(try_from) = &attrs.try_from {
+        return Ok(deserialize_from(input, &attrs, try_from, true));
+    }
+    match &attrs.tag_type {
+        TagType::External => deserialize_external(input, enumeration, &attrs),
         TagType::Adjacent { tag, content } => {
-            deserialize_adjacent(input, enumeration, tag, content)
+            deserialize_adjacent(input, enumeration, &attrs, tag, content)
         }
-        TagType::Internal(tag) => deserialize_internal(input, enumeration, tag),
+        TagType::Internal(tag) => deserialize_internal(input, enumeration, &attrs, tag),
         _ => Err(Error::new(
             Span::call_site(),
             "Only externally tagged enums are supported",
@@ -23,44 +34,378 @@ pub fn derive(input: &DeriveInput, enumeration: &DataEnum) -> Result<TokenStream
     }
 }
 
+/// Deserialize the enum as a value of another type, then convert it with `From`
+/// or, if `fallible`, with `TryFrom`.
+fn deserialize_from(
+    input: &DeriveInput,
+    attrs: &ContainerAttrs,
+    from: &Type,
+    fallible: bool,
+) -> TokenStream {
+    private!(__convert, __miniserde, __out, __value, __Visitor);
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let target = deserialized_type(input, attrs, &input.generics);
+    let mut generics = input.generics.clone();
+    let bounded_where_clause = generics.make_where_clause();
+    bounded_where_clause
+        .predicates
+        .push(parse_quote!(#from: #__miniserde::Deserialize));
+    let convert = if fallible {
+        bounded_where_clause
+            .predicates
+            .push(parse_quote!(#target: ::core::convert::TryFrom<#from>));
+        quote!(::core::convert::TryFrom::try_from(#__value).map_err(|_| #__miniserde::Error)?)
+    } else {
+        bounded_where_clause
+            .predicates
+            .push(parse_quote!(#target: ::core::convert::From<#from>));
+        quote!(::core::convert::From::from(#__value))
+    };
+    let deserialize_impl = deserialize_impl(
+        input,
+        attrs,
+        &input.generics,
+        &bounded_where_clause,
+        quote!(#__Visitor #ty_generics),
+    );
+
+    let visitor = from_visitor(
+        &input.generics,
+        bounded_where_clause,
+        &__Visitor,
+        &target,
+        from,
+        &__convert,
+    );
+
+    let alias = crate::miniserde_alias(attrs);
+    let allow = crate::allowed_lints();
+    quote! {
+        #allow
+        const _: () = {
+            #alias
+            struct #__Visitor #impl_generics #where_clause {
+                #__out: ::core::option::Option<#target>,
+            }
+
+            #deserialize_impl
+
+            fn #__convert #impl_generics (
+                #__out: &mut ::core::option::Option<#target>,
+                #__value: ::core::option::Option<#from>,
+            ) -> #__miniserde::Result<()> #bounded_where_clause {
+                let #__value = #__value.ok_or(#__miniserde::Error)?;
+                *#__out = ::core::option::Option::Some(#convert);
+                ::core::result::Result::Ok(())
+            }
+
+            #visitor
+        };
+    }
+}
+
+/// The Visitor impl of `visitor`, which fills its `out` field of type
+/// `Option<target>` by deserializing a `from` and passing it to `convert`,
+/// along with the Seq and Map impls it hands out. All of them are generic over
+/// `generics`, and their impls take the predicates of `where_clause`.
+pub fn from_visitor(
+    generics: &Generics,
+    where_clause: &WhereClause,
+    visitor: &Ident,
+    target: &TokenStream,
+    from: &Type,
+    convert: &Ident,
+) -> TokenStream {
+    private!(
+        __alloc, __error, __FromMap, __FromSeq, __key, __map, __miniserde, __out, __seq, __v, __value;
+        __a
+    );
+    let (impl_generics, ty_generics, struct_where_clause) = generics.split_for_impl();
+    let wrapper_generics = bound::with_lifetime_bound(generics, &__a);
+    let (wrapper_impl_generics, wrapper_ty_generics, _) = wrapper_generics.split_for_impl();
+
+    // Scalars are deserialized into a temporary right away.
+    let scalars = [
+        (quote!(null), None),
+        (quote!(boolean), Some(quote!(::core::primitive::bool))),
+        (quote!(string), Some(quote!(&::core::primitive::str))),
+        (quote!(negative), Some(quote!(::core::primitive::i64))),
+        (quote!(nonnegative), Some(quote!(::core::primitive::u64))),
+        (quote!(float), Some(quote!(::core::primitive::f64))),
+    ];
+    let scalars = scalars.iter().map(|(method, ty)| {
+        let (param, arg) = match ty {
+            Some(ty) => (quote!(#__v: #ty), quote!(#__v)),
+            None => (quote!(), quote!()),
+        };
+        quote! {
+            fn #method(&mut self, #param) -> #__miniserde::Result<()> {
+                let mut #__value = ::core::option::Option::None;
+                <#from as #__miniserde::Deserialize>::begin(&mut #__value).#method(#arg)?;
+                #convert(&mut self.#__out, #__value)
+            }
+        }
+    });
+
+    quote! {
+        #[automatically_derived]
+        impl #impl_generics #__miniserde::de::Visitor for #visitor #ty_generics #where_clause {
+            #(#scalars)*
+
+            fn seq(&mut self) -> #__miniserde::Result<#__alloc::boxed::Box<dyn #__miniserde::de::Seq + '_>> {
+                let #__value = #__alloc::boxed::Box::into_raw(#__alloc::boxed::Box::new(::core::option::Option::None));
+                match <#from as #__miniserde::Deserialize>::begin(unsafe { &mut *#__value }).seq() {
+                    ::core::result::Result::Ok(#__seq) => ::core::result::Result::Ok(#__alloc::boxed::Box::new(#__FromSeq {
+                        seq: ::core::mem::ManuallyDrop::new(#__seq),
+                        value: #__value,
+                        #__out: &mut self.#__out,
+                    })),
+                    ::core::result::Result::Err(#__error) => {
+                        ::core::mem::drop(unsafe { #__alloc::boxed::Box::from_raw(#__value) });
+                        ::core::result::Result::Err(#__error)
+                    }
+                }
+            }
+
+            fn map(&mut self) -> #__miniserde::Result<#__alloc::boxed::Box<dyn #__miniserde::de::Map + '_>> {
+                let #__value = #__alloc::boxed::Box::into_raw(#__alloc::boxed::Box::new(::core::option::Option::None));
+                match <#from as #__miniserde::Deserialize>::begin(unsafe { &mut *#__value }).map() {
+                    ::core::result::Result::Ok(#__map) => ::core::result::Result::Ok(#__alloc::boxed::Box::new(#__FromMap {
+                        map: ::core::mem::ManuallyDrop::new(#__map),
+                        value: #__value,
+                        #__out: &mut self.#__out,
+                    })),
+                    ::core::result::Result::Err(#__error) => {
+                        ::core::mem::drop(unsafe { #__alloc::boxed::Box::from_raw(#__value) });
+                        ::core::result::Result::Err(#__error)
+                    }
+                }
+            }
+        }
+
+        // The value is filled through a reference held by the sequence or
+        // map, so it is kept as a raw pointer rather than a Box, which would
+        // assert unique access each time the wrapper is moved. It is freed
+        // once the sequence or map is dropped.
+        struct #__FromSeq #wrapper_impl_generics #struct_where_clause {
+            seq: ::core::mem::ManuallyDrop<#__alloc::boxed::Box<dyn #__miniserde::de::Seq + #__a>>,
+            value: *mut ::core::option::Option<#from>,
+            #__out: &#__a mut ::core::option::Option<#target>,
+        }
+
+        #[automatically_derived]
+        impl #wrapper_impl_generics ::core::ops::Drop for #__FromSeq #wrapper_ty_generics #struct_where_clause {
+            fn drop(&mut self) {
+                unsafe {
+                    ::core::mem::ManuallyDrop::drop(&mut self.seq);
+                    ::core::mem::drop(#__alloc::boxed::Box::from_raw(self.value));
+                }
+            }
+        }
+
+        #[automatically_derived]
+        impl #wrapper_impl_generics #__miniserde::de::Seq for #__FromSeq #wrapper_ty_generics #where_clause {
+            fn element(&mut self) -> #__miniserde::Result<&mut dyn #__miniserde::de::Visitor> {
+                self.seq.element()
+            }
+
+            fn finish(&mut self) -> #__miniserde::Result<()> {
+                self.seq.finish()?;
+                #convert(self.#__out, unsafe { (*self.value).take() })
+            }
+        }
+
+        struct #__FromMap #wrapper_impl_generics #struct_where_clause {
+            map: ::core::mem::ManuallyDrop<#__alloc::boxed::Box<dyn #__miniserde::de::Map + #__a>>,
+            value: *mut ::core::option::Option<#from>,
+            #__out: &#__a mut ::core::option::Option<#target>,
+        }
+
+        #[automatically_derived]
+        impl #wrapper_impl_generics ::core::ops::Drop for #__FromMap #wrapper_ty_generics #struct_where_clause {
+            fn drop(&mut self) {
+                unsafe {
+                    ::core::mem::ManuallyDrop::drop(&mut self.map);
+                    ::core::mem::drop(#__alloc::boxed::Box::from_raw(self.value));
+                }
+            }
+        }
+
+        #[automatically_derived]
+        impl #wrapper_impl_generics #__miniserde::de::Map for #__FromMap #wrapper_ty_generics #where_clause {
+            fn key(&mut self, #__key: &::core::primitive::str) -> #__miniserde::Result<&mut dyn #__miniserde::de::Visitor> {
+                self.map.key(#__key)
+            }
+
+            fn finish(&mut self) -> #__miniserde::Result<()> {
+                self.map.finish()?;
+                #convert(self.#__out, unsafe { (*self.value).take() })
+            }
+        }
+    }
+}
+
+/// The types of the fields of a variant that are deserialized through their
+/// Deserialize impl or that take their Default value, along with that trait.
+fn deserialized_types(variant: &Variant) -> Result<Vec<(&Type, TokenStream)>> {
+    private!(__miniserde);
+    if attr::variant_attrs(variant)?.skip_deserializing {
+        return Ok(Vec::new());
+    }
+    let deserialize = quote!(#__miniserde::Deserialize);
+    let default_trait = quote!(::core::default::Default);
+    let mut types = Vec::new();
+    for VariantField {
+        field,
+        attrs,
+        default,
+        ..
+    } in field::fields(variant)?
+    {
+        if !attrs.skip_deserializing && attrs.with.deserialize.is_none() {
+            types.push((&field.ty, deserialize.clone()));
+        }
+        if let Some(DefaultValue::Trait) = default {
+            types.push((&field.ty, default_trait.clone()));
+        }
+    }
+    Ok(types)
+}
+
+/// The type of the values the enum is deserialized to: the remote enum if there
+/// is one.
+fn deserialized_type(
+    input: &DeriveInput,
+    attrs: &ContainerAttrs,
+    generics: &Generics,
+) -> TokenStream {
+    let ident = &input.ident;
+    let (_, ty_generics, _) = generics.split_for_impl();
+    match &attrs.remote {
+        Some(remote) => quote!(#remote),
+        None => quote!(#ident #ty_generics),
+    }
+}
+
+/// Hand out the visitor of the enum, which the place of the value is cast to:
+/// in the Deserialize impl of the enum or, for a remote enum, in a `deserialize`
+/// function usable with `with`.
+fn deserialize_impl(
+    input: &DeriveInput,
+    attrs: &ContainerAttrs,
+    generics: &Generics,
+    where_clause: impl ToTokens,
+    visitor: TokenStream,
+) -> TokenStream {
+    private!(__miniserde, __out);
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, _) = generics.split_for_impl();
+    match &attrs.remote {
+        Some(remote) => {
+            let vis = &input.vis;
+            let doc = format!(
+                "Deserializes a `{}` as defined by `{}`.",
+                quote!(#remote).to_string().replace(' ', ""),
+                ident.unraw(),
+            );
+            quote! {
+                #[automatically_derived]
+                impl #impl_generics #ident #ty_generics #where_clause {
+                    #[doc = #doc]
+                    #vis fn deserialize(#__out: &mut ::core::option::Option<#remote>) -> &mut dyn #__miniserde::de::Visitor {
+                        unsafe {
+                            &mut *{
+                                #__out
+                                    as *mut ::core::option::Option<#remote>
+                                    as *mut #visitor
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        None => quote! {
+            #[automatically_derived]
+            impl #impl_generics #__miniserde::Deserialize for #ident #ty_generics #where_clause {
+                fn begin(#__out: &mut ::core::option::Option<Self>) -> &mut dyn #__miniserde::de::Visitor {
+                    unsafe {
+                        &mut *{
+                            #__out
+                                as *mut ::core::option::Option<Self>
+                                as *mut #visitor
+                        }
+                    }
+                }
+            }
+        },
+    }
+}
+
 struct EnumVariants {
     struct_variant_names: Vec<String>,
     struct_variant_idents: Vec<Ident>,
     struct_names: Vec<Ident>,
+    struct_types: Vec<TokenStream>,
     structs: Vec<TokenStream>,
     unit_variant_names: Vec<String>,
     unit_variant_idents: Vec<Ident>,
 }
 
 impl EnumVariants {
-    fn new(ident: &Ident, enumeration: &DataEnum) -> Result<EnumVariants> {
-        let (unit_variants, struct_variants): (Vec<_>, Vec<_>) =
-            enumeration.variants.iter().partition(|v| {
-                if let Fields::Unit = &v.fields {
-                    true
-                } else {
-                    false
-                }
-            });
-        let struct_variant_names = struct_variants
-            .iter()
-            .cloned()
-            .map(attr::name_of_variant)
-            .collect::<Result<Vec<_>>>()?;
+    fn new(
+        input: &DeriveInput,
+        enumeration: &DataEnum,
+        attrs: &ContainerAttrs,
+        bounded_where_clause: &WhereClause,
+    ) -> Result<EnumVariants> {
+        let ident = &input.ident;
+        let mut unit_variants = Vec::new();
+        let mut unit_variant_names = Vec::new();
+        let mut struct_variants = Vec::new();
+        let mut struct_variant_names = Vec::new();
+        for variant in &enumeration.variants {
+            let attrs = attr::variant_attrs(variant)?;
+            if attrs.skip_deserializing {
+                continue;
+            }
+            if let Fields::Unit = &variant.fields {
+                unit_variants.push(variant);
+                unit_variant_names.push(attrs.name);
+            } else {
+                struct_variants.push(variant);
+                struct_variant_names.push(attrs.name);
+            }
+        }
+        let enum_path = attrs.enum_path(ident);
+        let (_, ty_generics, _) = input.generics.split_for_impl();
         let struct_names = struct_variants
             .iter()
             .map(|variant| {
-                Ident::new(
-                    &format!("__{}_{}_Struct", ident, variant.ident),
-                    Span::call_site(),
-                )
+                hygiene::ident(&format!(
+                    "{}_{}_Struct",
+                    ident.unraw(),
+                    variant.ident.unraw()
+                ))
             })
             .collect::<Vec<_>>();
         let structs = struct_variants
             .iter()
             .zip(struct_names.iter())
-            .map(|(variant, struct_ident)| variant_as_struct(variant, struct_ident, ident))
+            .map(|(variant, struct_ident)| {
+                variant_as_struct(
+                    variant,
+                    struct_ident,
+                    input,
+                    &enum_path,
+                    bounded_where_clause,
+                    attrs,
+                )
+            })
             .collect::<Result<Vec<_>>>()?;
+        let struct_types = struct_names
+            .iter()
+            .map(|name| quote!(#name #ty_generics))
+            .collect::<Vec<_>>();
         let struct_variant_idents = struct_variants
             .iter()
             .map(|variant| variant.ident.clone())
@@ -69,15 +414,11 @@ impl EnumVariants {
             .iter()
             .map(|v| v.ident.clone())
             .collect::<Vec<_>>();
-        let unit_variant_names = unit_variants
-            .iter()
-            .cloned()
-            .map(attr::name_of_variant)
-            .collect::<Result<Vec<_>>>()?;
         Ok(EnumVariants {
             struct_variant_names,
             struct_variant_idents,
             struct_names,
+            struct_types,
             structs,
             unit_variant_names,
             unit_variant_idents,
@@ -88,91 +429,134 @@ impl EnumVariants {
 pub fn deserialize_internal(
     input: &DeriveInput,
     enumeration: &DataEnum,
+    attrs: &ContainerAttrs,
     tag: &str,
 ) -> Result<TokenStream> {
+    private!(__alloc, __key, __map, __miniserde, __out, __State, __tag, __variant, __Visitor; __a);
     let ident = &input.ident;
+    let enum_path = attrs.enum_path(ident);
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let wrapper_generics = bound::with_lifetime_bound(&input.generics, &__a);
+    let (wrapper_impl_generics, wrapper_ty_generics, _) = wrapper_generics.split_for_impl();
+    let bounded_where_clause = bound::where_clause_for_enum(
+        &input.generics,
+        enumeration,
+        &attrs.bound,
+        |bound| &bound.deserialize,
+        deserialized_types,
+    )?;
     let EnumVariants {
         struct_variant_names,
         struct_names,
+        struct_types,
         structs,
         unit_variant_names,
         unit_variant_idents,
         ..
-    } = EnumVariants::new(ident, enumeration)?;
+    } = EnumVariants::new(input, enumeration, attrs, &bounded_where_clause)?;
+    let target = deserialized_type(input, attrs, &input.generics);
+    let deserialize_impl = deserialize_impl(
+        input,
+        attrs,
+        &input.generics,
+        &bounded_where_clause,
+        quote!(#__Visitor #ty_generics),
+    );
 
-    let ex = quote!(miniserde::export);
+    let unknown_key = unknown_key(attrs);
+    let duplicate_tag = duplicate_key(attrs, quote!(self.#__tag.is_some()));
 
+    let alias = crate::miniserde_alias(attrs);
+    let allow = crate::allowed_lints();
+    let array_helper = array::deserialize_helper(enumeration);
+    let cow_helper = cow::deserialize_helper(enumeration);
     Ok(quote! {
+        #allow
         const _: () = {
-            struct __Visitor {
-                __out: #ex::Option<#ident>,
+            #alias
+            #array_helper
+            #cow_helper
+            struct #__Visitor #impl_generics #where_clause {
+                #__out: ::core::option::Option<#target>,
             }
 
-            impl miniserde::Deserialize for #ident {
-                fn begin(__out: &mut #ex::Option<Self>) -> &mut dyn miniserde::de::Visitor {
-                    unsafe {
-                        &mut *{
-                            __out
-                                as *mut #ex::Option<Self>
-                                as *mut __Visitor
-                        }
-                    }
-                }
-            }
+            #deserialize_impl
 
-            impl miniserde::de::Visitor for __Visitor {
-                fn map(&mut self) -> miniserde::Result<#ex::Box<dyn miniserde::de::Map + '_>> {
-                    Ok(#ex::Box::new(__State {
-                        #(#struct_names: None,)*
-                        __tag: None,
-                        __map: None,
-                        __out: &mut self.__out,
+            #[automatically_derived]
+            impl #impl_generics #__miniserde::de::Visitor for #__Visitor #ty_generics #bounded_where_clause {
+                fn map(&mut self) -> #__miniserde::Result<#__alloc::boxed::Box<dyn #__miniserde::de::Map + '_>> {
+                    ::core::result::Result::Ok(#__alloc::boxed::Box::new(#__State {
+                        #(#struct_names: ::core::option::Option::None,)*
+                        #__tag: ::core::option::Option::None,
+                        #__map: ::core::option::Option::None,
+                        #__out: &mut self.#__out,
                     }))
                 }
             }
 
-            struct __State<'a> {
-                #(#[allow(non_snake_case)] #struct_names: #ex::Option<#struct_names>,)*
-                __tag: #ex::Option<String>,
-                __map: #ex::Option<#ex::Box<dyn miniserde::de::Map + 'a>>,
-                __out: &'a mut #ex::Option<#ident>,
+            struct #__State #wrapper_impl_generics #where_clause {
+                #(#[allow(non_snake_case)] #struct_names: ::core::option::Option<#struct_types>,)*
+                #__tag: ::core::option::Option<#__alloc::string::String>,
+                #__map: ::core::option::Option<#__alloc::boxed::Box<dyn #__miniserde::de::Map + #__a>>,
+                #__out: &#__a mut ::core::option::Option<#target>,
             }
 
             #(#structs)*
 
-            impl<'a> miniserde::de::Map for __State<'a> {
-                fn key(&mut self, k: &#ex::str) -> miniserde::Result<&mut dyn miniserde::de::Visitor> {
-                    if k == #tag {
-                        return Ok(<String as miniserde::Deserialize>::begin(&mut self.__tag));
+            #[automatically_derived]
+            impl #wrapper_impl_generics #__State #wrapper_ty_generics #bounded_where_clause {
+                fn begin_map(&mut self) -> #__miniserde::Result<()> {
+                    let #__variant = self.#__tag.as_ref().ok_or(#__miniserde::Error)?;
+                    match #__variant.as_str() {
+                        #(#struct_variant_names => {
+                            self.#__map = ::core::option::Option::Some(<#struct_types as #__miniserde::Deserialize>::begin(
+                                unsafe {&mut *(&mut self.#struct_names as *mut ::core::option::Option<#struct_types>)}
+                            ).map()?);
+                            ::core::result::Result::Ok(())
+                        })*
+                        _ => ::core::result::Result::Err(#__miniserde::Error),
+                    }
+                }
+            }
+
+            #[automatically_derived]
+            impl #wrapper_impl_generics #__miniserde::de::Map for #__State #wrapper_ty_generics #bounded_where_clause {
+                fn key(&mut self, #__key: &::core::primitive::str) -> #__miniserde::Result<&mut dyn #__miniserde::de::Visitor> {
+                    if #__key == #tag {
+                        #duplicate_tag
+                        return ::core::result::Result::Ok(<#__alloc::string::String as #__miniserde::Deserialize>::begin(&mut self.#__tag));
+                    }
+                    match self.#__tag.as_ref().map(#__alloc::string::String::as_str) {
+                        #(::core::option::Option::Some(#unit_variant_names) => return #unknown_key,)*
+                        _ => (),
                     }
-                    if self.__map.is_none() {
-                        let tag = self.__tag.as_ref().ok_or(miniserde::Error)?;
-                        self.__map.replace(match tag.as_ref() {
-                            #(#struct_variant_names => <#struct_names as miniserde::Deserialize>::begin(
-                                    unsafe {&mut *(&mut self.#struct_names as *mut #ex::Option<#struct_names>)}
-                            ).map()?,)*
-                            _ => return #ex::Err(miniserde::Error),
-                        });
+                    if self.#__map.is_none() {
+                        self.begin_map()?;
                     }
-                    self.__map.as_mut().ok_or(miniserde::Error)?.key(k)
+                    self.#__map.as_mut().ok_or(#__miniserde::Error)?.key(#__key)
                 }
 
-                fn finish(&mut self) -> miniserde::Result<()> {
-                    let tag = self.__tag.take().ok_or(miniserde::Error)?;
-                    match tag.as_str() {
+                fn finish(&mut self) -> #__miniserde::Result<()> {
+                    match self.#__tag.as_ref().ok_or(#__miniserde::Error)?.as_str() {
                         #(#unit_variant_names => {
-                            self.__out.replace(#ident::#unit_variant_idents);
-                            return #ex::Ok(());
+                            self.#__out.replace(#enum_path::#unit_variant_idents);
+                            return ::core::result::Result::Ok(());
                         })*
                         _ => (),
                     }
-                    self.__map.take().ok_or(miniserde::Error)?.finish()?;
-                    match tag.as_str() {
+                    // A struct variant whose fields all have defaults may
+                    // contain nothing but the tag.
+                    if self.#__map.is_none() {
+                        self.begin_map()?;
+                    }
+                    self.#__map.take().ok_or(#__miniserde::Error)?.finish()?;
+                    let #__variant = self.#__tag.take().ok_or(#__miniserde::Error)?;
+                    match #__variant.as_str() {
                         #(#struct_variant_names => {
-                            self.__out.replace(self.#struct_names.take().ok_or(miniserde::Error)?.as_enum());
-                            #ex::Ok(())
+                            self.#__out.replace(self.#struct_names.take().ok_or(#__miniserde::Error)?.as_enum());
+                            ::core::result::Result::Ok(())
                         })*
-                        _ => #ex::Err(miniserde::Error)
+                        _ => ::core::result::Result::Err(#__miniserde::Error)
                     }
                 }
             }
@@ -183,86 +567,119 @@ pub fn deserialize_internal(
 pub fn deserialize_adjacent(
     input: &DeriveInput,
     enumeration: &DataEnum,
+    attrs: &ContainerAttrs,
     tag: &str,
     content: &str,
 ) -> Result<TokenStream> {
+    private!(__alloc, __key, __miniserde, __out, __State, __tag, __value, __Visitor; __a);
     let ident = &input.ident;
+    let enum_path = attrs.enum_path(ident);
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let wrapper_generics = bound::with_lifetime_bound(&input.generics, &__a);
+    let (wrapper_impl_generics, wrapper_ty_generics, _) = wrapper_generics.split_for_impl();
+    let bounded_where_clause = bound::where_clause_for_enum(
+        &input.generics,
+        enumeration,
+        &attrs.bound,
+        |bound| &bound.deserialize,
+        deserialized_types,
+    )?;
     let EnumVariants {
         struct_variant_names,
         struct_names,
+        struct_types,
         structs,
         unit_variant_names,
         unit_variant_idents,
         ..
-    } = EnumVariants::new(ident, enumeration)?;
+    } = EnumVariants::new(input, enumeration, attrs, &bounded_where_clause)?;
+    let target = deserialized_type(input, attrs, &input.generics);
+    let deserialize_impl = deserialize_impl(
+        input,
+        attrs,
+        &input.generics,
+        &bounded_where_clause,
+        quote!(#__Visitor #ty_generics),
+    );
 
-    let ex = quote!(miniserde::export);
+    let unknown_key = unknown_key(attrs);
+    let duplicate_tag = duplicate_key(attrs, quote!(self.#__tag.is_some()));
+    let duplicate_content = struct_names
+        .iter()
+        .map(|name| duplicate_key(attrs, quote!(self.#name.is_some())))
+        .collect::<Vec<_>>();
 
+    let alias = crate::miniserde_alias(attrs);
+    let allow = crate::allowed_lints();
+    let array_helper = array::deserialize_helper(enumeration);
+    let cow_helper = cow::deserialize_helper(enumeration);
     Ok(quote! {
+        #allow
         const _: () = {
-            struct __Visitor {
-                __out: #ex::Option<#ident>,
+            #alias
+            #array_helper
+            #cow_helper
+            struct #__Visitor #impl_generics #where_clause {
+                #__out: ::core::option::Option<#target>,
             }
 
-            impl miniserde::Deserialize for #ident {
-                fn begin(__out: &mut #ex::Option<Self>) -> &mut dyn miniserde::de::Visitor {
-                    unsafe {
-                        &mut *{
-                            __out
-                                as *mut #ex::Option<Self>
-                                as *mut __Visitor
-                        }
-                    }
-                }
-            }
+            #deserialize_impl
 
-            impl miniserde::de::Visitor for __Visitor {
-                fn map(&mut self) -> miniserde::Result<#ex::Box<dyn miniserde::de::Map + '_>> {
-                    Ok(#ex::Box::new(__State {
-                        #(#struct_names: None,)*
-                        __tag: None,
-                        __out: &mut self.__out,
+            #[automatically_derived]
+            impl #impl_generics #__miniserde::de::Visitor for #__Visitor #ty_generics #bounded_where_clause {
+                fn map(&mut self) -> #__miniserde::Result<#__alloc::boxed::Box<dyn #__miniserde::de::Map + '_>> {
+                    ::core::result::Result::Ok(#__alloc::boxed::Box::new(#__State {
+                        #(#struct_names: ::core::option::Option::None,)*
+                        #__tag: ::core::option::Option::None,
+                        #__out: &mut self.#__out,
                     }))
                 }
             }
 
-            struct __State<'a> {
-                #(#[allow(non_snake_case)] #struct_names: #ex::Option<#struct_names>,)*
-                __tag: Option<String>,
-                __out: &'a mut #ex::Option<#ident>,
+            struct #__State #wrapper_impl_generics #where_clause {
+                #(#[allow(non_snake_case)] #struct_names: ::core::option::Option<#struct_types>,)*
+                #__tag: ::core::option::Option<#__alloc::string::String>,
+                #__out: &#__a mut ::core::option::Option<#target>,
             }
 
             #(#structs)*
 
-            impl<'a> miniserde::de::Map for __State<'a> {
-                fn key(&mut self, k: &#ex::str) -> miniserde::Result<&mut dyn miniserde::de::Visitor> {
-                    match k {
-                        #tag => Ok(<String as miniserde::Deserialize>::begin(&mut self.__tag)),
+            #[automatically_derived]
+            impl #wrapper_impl_generics #__miniserde::de::Map for #__State #wrapper_ty_generics #bounded_where_clause {
+                fn key(&mut self, #__key: &::core::primitive::str) -> #__miniserde::Result<&mut dyn #__miniserde::de::Visitor> {
+                    match #__key {
+                        #tag => {
+                            #duplicate_tag
+                            ::core::result::Result::Ok(<#__alloc::string::String as #__miniserde::Deserialize>::begin(&mut self.#__tag))
+                        }
                         #content => {
-                            match self.__tag.as_ref().map(|s| s.as_str()) {
-                                #(Some(#struct_variant_names) => Ok(<#struct_names as miniserde::Deserialize>::begin(&mut self.#struct_names)),)*
-                                _ => #ex::Err(miniserde::Error),
+                            match self.#__tag.as_ref().map(#__alloc::string::String::as_str) {
+                                #(::core::option::Option::Some(#struct_variant_names) => {
+                                    #duplicate_content
+                                    ::core::result::Result::Ok(<#struct_types as #__miniserde::Deserialize>::begin(&mut self.#struct_names))
+                                })*
+                                _ => ::core::result::Result::Err(#__miniserde::Error),
                             }
                         }
-                        _ => #ex::Err(miniserde::Error),
+                        _ => #unknown_key,
                     }
                 }
 
-                fn finish(&mut self) -> miniserde::Result<()> {
-                    match self.__tag.as_ref().map(|s| s.as_str()) {
-                        #(Some(#unit_variant_names) => {
-                            self.__out.replace(#ident::#unit_variant_idents);
-                            Ok(())
+                fn finish(&mut self) -> #__miniserde::Result<()> {
+                    match self.#__tag.as_ref().map(#__alloc::string::String::as_str) {
+                        #(::core::option::Option::Some(#unit_variant_names) => {
+                            self.#__out.replace(#enum_path::#unit_variant_idents);
+                            ::core::result::Result::Ok(())
                         })*
-                        #(Some(#struct_variant_names) => {
-                            if let Some(val) = self.#struct_names.take() {
-                                self.__out.replace(val.as_enum());
-                                #ex::Ok(())
+                        #(::core::option::Option::Some(#struct_variant_names) => {
+                            if let ::core::option::Option::Some(#__value) = self.#struct_names.take() {
+                                self.#__out.replace(#__value.as_enum());
+                                ::core::result::Result::Ok(())
                             } else {
-                                #ex::Err(miniserde::Error)
+                                ::core::result::Result::Err(#__miniserde::Error)
                             }
                         })*
-                        _ => #ex::Err(miniserde::Error),
+                        _ => ::core::result::Result::Err(#__miniserde::Error),
                     }
                 }
             }
@@ -270,224 +687,540 @@ pub fn deserialize_adjacent(
     })
 }
 
-pub fn deserialize_external(input: &DeriveInput, enumeration: &DataEnum) -> Result<TokenStream> {
+pub fn deserialize_external(
+    input: &DeriveInput,
+    enumeration: &DataEnum,
+    attrs: &ContainerAttrs,
+) -> Result<TokenStream> {
+    private!(__alloc, __key, __miniserde, __out, __seen, __State, __value, __variant, __Visitor; __a);
     let ident = &input.ident;
 
     let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
-    let wrapper_generics = bound::with_lifetime_bound(&input.generics, "'__a");
+    let wrapper_generics = bound::with_lifetime_bound(&input.generics, &__a);
     let (wrapper_impl_generics, wrapper_ty_generics, _) = wrapper_generics.split_for_impl();
-    let bound = parse_quote!(miniserde::Deserialize);
-    let bounded_where_clause = bound::where_clause_with_bound(&input.generics, bound);
+    let bounded_where_clause = bound::where_clause_for_enum(
+        &input.generics,
+        enumeration,
+        &attrs.bound,
+        |bound| &bound.deserialize,
+        deserialized_types,
+    )?;
 
     let EnumVariants {
         struct_variant_names,
         struct_variant_idents,
-        struct_names,
+        struct_types,
         structs,
         unit_variant_names,
         unit_variant_idents,
         ..
-    } = EnumVariants::new(ident, enumeration)?;
+    } = EnumVariants::new(input, enumeration, attrs, &bounded_where_clause)?;
+    let enum_path = attrs.enum_path(ident);
+    let target = deserialized_type(input, attrs, &input.generics);
+    let deserialize_impl = deserialize_impl(
+        input,
+        attrs,
+        &input.generics,
+        &bounded_where_clause,
+        quote!(#__Visitor #ty_generics),
+    );
 
-    let ex = quote!(miniserde::export);
+    let unknown_key = unknown_key(attrs);
+    let duplicate_variant = struct_variant_idents
+        .iter()
+        .map(|var| duplicate_key(attrs, quote!(self.#var.is_some())))
+        .collect::<Vec<_>>();
+    // In strict mode, the map must hold a single key: the variant.
+    let (seen_field, seen_init, seen_check) = if attrs.deny_unknown_fields {
+        (
+            quote!(#__seen: ::core::primitive::bool,),
+            quote!(#__seen: false,),
+            quote! {
+                if self.#__seen {
+                    return ::core::result::Result::Err(#__miniserde::Error);
+                }
+                self.#__seen = true;
+            },
+        )
+    } else {
+        (quote!(), quote!(), quote!())
+    };
 
+    let alias = crate::miniserde_alias(attrs);
+    let allow = crate::allowed_lints();
+    let array_helper = array::deserialize_helper(enumeration);
+    let cow_helper = cow::deserialize_helper(enumeration);
     Ok(quote! {
+        #allow
         const _: () = {
-            struct __Visitor #impl_generics #where_clause {
-                __out: #ex::Option<#ident #ty_generics>,
+            #alias
+            #array_helper
+            #cow_helper
+            struct #__Visitor #impl_generics #where_clause {
+                #__out: ::core::option::Option<#target>,
             }
 
-            impl #impl_generics miniserde::Deserialize for #ident #ty_generics #bounded_where_clause {
-                fn begin(__out: &mut #ex::Option<Self>) -> &mut dyn miniserde::de::Visitor {
-                    unsafe {
-                        &mut *{
-                            __out
-                                as *mut #ex::Option<Self>
-                                as *mut __Visitor #ty_generics
-                        }
-                    }
-                }
-            }
+            #deserialize_impl
 
-            impl #impl_generics miniserde::de::Visitor for __Visitor #ty_generics #bounded_where_clause {
-                fn map(&mut self) -> miniserde::Result<#ex::Box<dyn miniserde::de::Map + '_>> {
-                    Ok(#ex::Box::new(__State{
-                        __out: &mut self.__out,
-                        #(#struct_variant_idents: None,)*
+            #[automatically_derived]
+            impl #impl_generics #__miniserde::de::Visitor for #__Visitor #ty_generics #bounded_where_clause {
+                fn map(&mut self) -> #__miniserde::Result<#__alloc::boxed::Box<dyn #__miniserde::de::Map + '_>> {
+                    ::core::result::Result::Ok(#__alloc::boxed::Box::new(#__State{
+                        #__out: &mut self.#__out,
+                        #(#struct_variant_idents: ::core::option::Option::None,)*
+                        #seen_init
                     }))
                 }
 
-                fn string(&mut self, s: &str) -> miniserde::Result<()> {
-                    match s {
+                fn string(&mut self, #__variant: &::core::primitive::str) -> #__miniserde::Result<()> {
+                    match #__variant {
                         #(#unit_variant_names => {
-                            self.__out = Some(#ident::#unit_variant_idents);
-                            Ok(())
+                            self.#__out = ::core::option::Option::Some(#enum_path::#unit_variant_idents);
+                            ::core::result::Result::Ok(())
                         })*
-                        _ => Err(miniserde::Error)
+                        _ => ::core::result::Result::Err(#__miniserde::Error)
                     }
                 }
             }
 
             #[allow(non_snake_case)]
-            struct __State #wrapper_impl_generics #where_clause {
-                #(#[allow(non_snake_case)] #struct_variant_idents: #ex::Option<#struct_names>,)*
-                __out: &'__a mut #ex::Option<#ident #ty_generics>,
+            struct #__State #wrapper_impl_generics #where_clause {
+                #(#[allow(non_snake_case)] #struct_variant_idents: ::core::option::Option<#struct_types>,)*
+                #__out: &#__a mut ::core::option::Option<#target>,
+                #seen_field
             }
 
             #(#structs)*
 
-            impl #wrapper_impl_generics miniserde::de::Map for __State #wrapper_ty_generics #bounded_where_clause {
-                fn key(&mut self, k: &#ex::str) -> miniserde::Result<&mut dyn miniserde::de::Visitor> {
-                    match k {
+            #[automatically_derived]
+            impl #wrapper_impl_generics #__miniserde::de::Map for #__State #wrapper_ty_generics #bounded_where_clause {
+                fn key(&mut self, #__key: &::core::primitive::str) -> #__miniserde::Result<&mut dyn #__miniserde::de::Visitor> {
+                    #seen_check
+                    match #__key {
                         #(
-                            #struct_variant_names => #ex::Ok(#struct_names::begin(&mut self.#struct_variant_idents)),
+                            #struct_variant_names => {
+                                #duplicate_variant
+                                ::core::result::Result::Ok(<#struct_types as #__miniserde::Deserialize>::begin(&mut self.#struct_variant_idents))
+                            }
                         )*
-                            _ => #ex::Ok(miniserde::de::Visitor::ignore()),
+                            _ => #unknown_key,
                     }
                 }
 
-                fn finish(&mut self) -> miniserde::Result<()> {
+                fn finish(&mut self) -> #__miniserde::Result<()> {
                     #(
-                        if let Some(val) = self.#struct_variant_idents.take() {
-                            *self.__out = #ex::Some(val.as_enum());
-                            return #ex::Ok(());
+                        if let ::core::option::Option::Some(#__value) = self.#struct_variant_idents.take() {
+                            *self.#__out = ::core::option::Option::Some(#__value.as_enum());
+                            return ::core::result::Result::Ok(());
                         }
                     )*
-                    #ex::Err(miniserde::Error)
+                    ::core::result::Result::Err(#__miniserde::Error)
                 }
             }
         };
     })
 }
 
+/// Rejects a key that was already seen in the map when the container asks for it.
+fn duplicate_key(attrs: &ContainerAttrs, seen: TokenStream) -> TokenStream {
+    private!(__miniserde);
+    if attrs.deny_duplicate_keys {
+        quote! {
+            if #seen {
+                return ::core::result::Result::Err(#__miniserde::Error);
+            }
+        }
+    } else {
+        quote!()
+    }
+}
+
+/// The result of looking up a key that belongs neither to the representation of
+/// the enum nor to a field.
+fn unknown_key(attrs: &ContainerAttrs) -> TokenStream {
+    private!(__miniserde);
+    if attrs.deny_unknown_fields {
+        quote!(::core::result::Result::Err(#__miniserde::Error))
+    } else {
+        quote!(::core::result::Result::Ok(
+            #__miniserde::de::Visitor::ignore()
+        ))
+    }
+}
+
 pub fn variant_as_struct(
     variant: &Variant,
     ident: &Ident,
-    enum_ident: &Ident,
+    input: &DeriveInput,
+    enum_path: &Path,
+    bounded_where_clause: &WhereClause,
+    attrs: &ContainerAttrs,
 ) -> Result<TokenStream> {
+    let fields = field::fields(variant)?;
     match &variant.fields {
-        Fields::Named(fields) => named_fields_as_struct(variant, fields, ident, enum_ident),
-        Fields::Unnamed(fields) => unnamed_fields_as_struct(variant, fields, ident, enum_ident),
+        Fields::Named(_) => named_fields_as_struct(
+            variant,
+            &fields,
+            ident,
+            input,
+            enum_path,
+            bounded_where_clause,
+            attrs,
+        ),
+        Fields::Unnamed(_) => unnamed_fields_as_struct(
+            variant,
+            &fields,
+            ident,
+            input,
+            enum_path,
+            bounded_where_clause,
+            attrs,
+        ),
         _ => unreachable!(),
     }
 }
 
 pub fn named_fields_as_struct(
     variant: &Variant,
-    fields: &FieldsNamed,
+    fields: &[VariantField],
     ident: &Ident,
-    enum_ident: &Ident,
+    input: &DeriveInput,
+    enum_path: &Path,
+    bounded_where_clause: &WhereClause,
+    container_attrs: &ContainerAttrs,
 ) -> Result<TokenStream> {
+    private!(
+        __alloc,
+        __flatten,
+        __key,
+        __map,
+        __marker,
+        __miniserde,
+        __missing,
+        __out,
+        __seen,
+        __State,
+        __state,
+        __value,
+        __Visitor;
+        __a
+    );
     let variant_ident = &variant.ident;
-    let as_enum = {
-        let fieldname = fields.named.iter().map(|f| &f.ident).collect::<Vec<_>>();
-        quote! {
-            #enum_ident::#variant_ident {
-                #(
-                    #fieldname: self.#fieldname,
-                    )*
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let wrapper_generics = bound::with_lifetime_bound(&input.generics, &__a);
+    let (wrapper_impl_generics, wrapper_ty_generics, _) = wrapper_generics.split_for_impl();
+    let target = deserialized_type(input, container_attrs, &input.generics);
+    let marker = marker_type(input);
+    let mut fieldname = Vec::new();
+    let mut fieldty = Vec::new();
+    let mut missing = Vec::new();
+    let mut keyed = Vec::new();
+    let mut state_field = Vec::new();
+    let mut state_init = Vec::new();
+    let mut taken = Vec::new();
+    let mut flatten = None;
+    let mut skipped_fieldname = Vec::new();
+    let mut skipped_value = Vec::new();
+    for VariantField {
+        field,
+        ident: field_ident,
+        attrs,
+        with,
+        default,
+        ..
+    } in fields
+    {
+        let default = default.as_ref().map(|default| match default {
+            DefaultValue::Path(path) => quote!(#path()),
+            DefaultValue::Trait => quote!(::core::default::Default::default()),
+        });
+        if attrs.skip_deserializing {
+            skipped_fieldname.push(field_ident);
+            skipped_value.push(default);
+            continue;
+        }
+        let ty = &field.ty;
+        fieldname.push(field_ident);
+        fieldty.push(ty);
+        let begin = match &with.deserialize {
+            Some(path) => quote!(#path),
+            None => quote!(<#ty as #__miniserde::Deserialize>::begin),
+        };
+        // A missing field is deserialized from null, which gives `None` for an
+        // optional field like in miniserde's own derive, and an error otherwise.
+        missing.push(default.unwrap_or_else(|| {
+            quote! {{
+                let mut #__missing = ::core::option::Option::None;
+                let _ = #begin(&mut #__missing).null();
+                match #__missing {
+                    ::core::option::Option::Some(#__value) => #__value,
+                    ::core::option::Option::None => return ::core::result::Result::Err(#__miniserde::Error),
+                }
+            }}
+        }));
+        if attrs.flatten {
+            if flatten.is_some() {
+                return Err(Error::new_spanned(
+                    field,
+                    "only one field of a variant can be flattened",
+                ));
             }
+            state_field.push(quote!(#field_ident: *mut ::core::option::Option<#ty>));
+            state_init.push(quote! {
+                #field_ident: #__alloc::boxed::Box::into_raw(#__alloc::boxed::Box::new(::core::option::Option::None))
+            });
+            taken.push(quote!(unsafe { (*self.#field_ident).take() }));
+            flatten = Some((field_ident, begin));
+            continue;
         }
+        state_field.push(quote!(#field_ident: ::core::option::Option<#ty>));
+        state_init.push(quote!(#field_ident: ::core::option::Option::None));
+        taken.push(quote!(self.#field_ident.take()));
+        let name = &attrs.name;
+        let index = keyed.len();
+        let duplicate = duplicate_key(container_attrs, quote!(self.#__seen[#index]));
+        keyed.push(quote! {
+            #name => {
+                #duplicate
+                self.#__seen[#index] = true;
+                ::core::result::Result::Ok(#begin(&mut self.#field_ident))
+            }
+        });
+    }
+    let key_count = keyed.len();
+    // Keys that do not belong to any field are passed on to the flattened
+    // field. Its map borrows the value it fills, which is allocated on its own
+    // so that the state can be moved, and freed once the map is dropped.
+    let (state, begin_flatten, unknown_key, drop_flatten) = match flatten {
+        Some((field_ident, begin)) => (
+            quote!(mut #__state),
+            quote! {
+                #__state.#__flatten = ::core::option::Option::Some(
+                    #begin(unsafe { &mut *#__state.#field_ident }).map()?,
+                );
+            },
+            quote!(self.#__flatten.as_mut().ok_or(#__miniserde::Error)?.key(#__key)),
+            quote! {
+                #[automatically_derived]
+                impl #wrapper_impl_generics ::core::ops::Drop for #__State #wrapper_ty_generics #where_clause {
+                    fn drop(&mut self) {
+                        self.#__flatten = ::core::option::Option::None;
+                        ::core::mem::drop(unsafe { #__alloc::boxed::Box::from_raw(self.#field_ident) });
+                    }
+                }
+            },
+        ),
+        None => (
+            quote!(#__state),
+            quote!(),
+            unknown_key(container_attrs),
+            quote!(),
+        ),
     };
-    let as_struct = syn::ItemStruct {
-        attrs: variant.attrs.clone(),
-        vis: syn::Visibility::Inherited,
-        struct_token: Default::default(),
-        ident: ident.clone(),
-        generics: Default::default(),
-        fields: variant.fields.clone(),
-        semi_token: None,
-    };
+    let forwarded = attr::forwarded_attrs(&variant.attrs);
     Ok(quote! {
-        #[derive(Deserialize)]
-        #as_struct
+        #(#forwarded)*
+        struct #ident #impl_generics #where_clause {
+            #(#fieldname: #fieldty,)*
+            #__marker: #marker,
+        }
 
-        impl #ident {
-            fn as_enum(self) -> #enum_ident {
-                #as_enum
+        #[automatically_derived]
+        impl #impl_generics #ident #ty_generics #where_clause {
+            fn as_enum(self) -> #target {
+                #enum_path::#variant_ident {
+                    #(
+                        #fieldname: self.#fieldname,
+                        )*
+                    #(
+                        #skipped_fieldname: #skipped_value,
+                        )*
+                }
             }
         }
+
+        const _: () = {
+            struct #__Visitor #impl_generics #where_clause {
+                #__out: ::core::option::Option<#ident #ty_generics>,
+            }
+
+            #[automatically_derived]
+            impl #impl_generics #__miniserde::Deserialize for #ident #ty_generics #bounded_where_clause {
+                fn begin(#__out: &mut ::core::option::Option<Self>) -> &mut dyn #__miniserde::de::Visitor {
+                    unsafe {
+                        &mut *{
+                            #__out
+                                as *mut ::core::option::Option<Self>
+                                as *mut #__Visitor #ty_generics
+                        }
+                    }
+                }
+            }
+
+            #[automatically_derived]
+            impl #impl_generics #__miniserde::de::Visitor for #__Visitor #ty_generics #bounded_where_clause {
+                fn map(&mut self) -> #__miniserde::Result<#__alloc::boxed::Box<dyn #__miniserde::de::Map + '_>> {
+                    let #state = #__alloc::boxed::Box::new(#__State {
+                        #__flatten: ::core::option::Option::None,
+                        #(#state_init,)*
+                        #__seen: [false; #key_count],
+                        #__out: &mut self.#__out,
+                    });
+                    #begin_flatten
+                    ::core::result::Result::Ok(#__state)
+                }
+            }
+
+            struct #__State #wrapper_impl_generics #where_clause {
+                #__flatten: ::core::option::Option<#__alloc::boxed::Box<dyn #__miniserde::de::Map + #__a>>,
+                #(#state_field,)*
+                #__seen: [::core::primitive::bool; #key_count],
+                #__out: &#__a mut ::core::option::Option<#ident #ty_generics>,
+            }
+
+            #[automatically_derived]
+            impl #wrapper_impl_generics #__miniserde::de::Map for #__State #wrapper_ty_generics #bounded_where_clause {
+                fn key(&mut self, #__key: &::core::primitive::str) -> #__miniserde::Result<&mut dyn #__miniserde::de::Visitor> {
+                    match #__key {
+                        #(#keyed)*
+                        _ => #unknown_key,
+                    }
+                }
+
+                fn finish(&mut self) -> #__miniserde::Result<()> {
+                    if let ::core::option::Option::Some(mut #__map) = self.#__flatten.take() {
+                        #__map.finish()?;
+                    }
+                    *self.#__out = ::core::option::Option::Some(#ident {
+                        #(#fieldname: match #taken {
+                            ::core::option::Option::Some(#__value) => #__value,
+                            ::core::option::Option::None => #missing,
+                        },)*
+                        #__marker: ::core::marker::PhantomData,
+                    });
+                    ::core::result::Result::Ok(())
+                }
+            }
+
+            #drop_flatten
+        };
     })
 }
 
 pub fn unnamed_fields_as_struct(
     variant: &Variant,
-    fields: &FieldsUnnamed,
+    fields: &[VariantField],
     ident: &Ident,
-    enum_ident: &Ident,
+    input: &DeriveInput,
+    enum_path: &Path,
+    bounded_where_clause: &WhereClause,
+    container_attrs: &ContainerAttrs,
 ) -> Result<TokenStream> {
+    private!(
+        __alloc, __current, __marker, __miniserde, __out, __State, __state, __value, __Visitor;
+        __a
+    );
     let variant_ident = &variant.ident;
-    let field_idents = (0..fields.unnamed.len())
-        .map(|x| Ident::new(&format!("__f{}", x), Span::call_site()))
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let wrapper_generics = bound::with_lifetime_bound(&input.generics, &__a);
+    let (wrapper_impl_generics, wrapper_ty_generics, _) = wrapper_generics.split_for_impl();
+    let target = deserialized_type(input, container_attrs, &input.generics);
+    let marker = marker_type(input);
+    let field_idents = fields.iter().map(|f| &f.ident).collect::<Vec<_>>();
+    let field_types = fields.iter().map(|f| &f.field.ty).collect::<Vec<_>>();
+    let begin = fields
+        .iter()
+        .map(|f| {
+            let ty = &f.field.ty;
+            match &f.with.deserialize {
+                Some(path) => quote!(#path),
+                None => quote!(<#ty as #__miniserde::Deserialize>::begin),
+            }
+        })
         .collect::<Vec<_>>();
-    let field_types = fields.unnamed.iter().map(|f| &f.ty).collect::<Vec<_>>();
+    // A newtype variant is deserialized in place of its helper struct, so both
+    // must have the same layout.
+    let repr = if fields.len() == 1 {
+        quote!(#[repr(transparent)])
+    } else {
+        quote!()
+    };
+    let forwarded = attr::forwarded_attrs(&variant.attrs);
     let as_struct = quote! {
-        struct #ident {
+        #(#forwarded)*
+        #repr
+        struct #ident #impl_generics #where_clause {
             #(#field_idents: #field_types,)*
+            #__marker: #marker,
         }
     };
-    let ex = quote!(miniserde::export);
-    let de_impl = if fields.unnamed.len() == 1 {
+    let de_impl = if fields.len() == 1 {
         let ty = field_types[0];
+        let begin = &begin[0];
         quote! {
-            impl miniserde::Deserialize for #ident {
-                fn begin(__out: &mut #ex::Option<Self>) -> &mut dyn miniserde::de::Visitor {
-                    <#ty as miniserde::Deserialize>::begin(unsafe {&mut *{__out as *mut #ex::Option<Self> as *mut #ex::Option<#ty>}})
+            #[automatically_derived]
+            impl #impl_generics #__miniserde::Deserialize for #ident #ty_generics #bounded_where_clause {
+                fn begin(#__out: &mut ::core::option::Option<Self>) -> &mut dyn #__miniserde::de::Visitor {
+                    #begin(unsafe {&mut *{#__out as *mut ::core::option::Option<Self> as *mut ::core::option::Option<#ty>}})
                 }
             }
         }
     } else {
         let index = 0usize..;
         quote! {
-            struct __Visitor {
-                __out: #ex::Option<#ident>,
+            struct #__Visitor #impl_generics #where_clause {
+                #__out: ::core::option::Option<#ident #ty_generics>,
             }
 
-            impl miniserde::Deserialize for #ident {
-                fn begin(__out: &mut #ex::Option<Self>) -> &mut dyn miniserde::de::Visitor {
+            #[automatically_derived]
+            impl #impl_generics #__miniserde::Deserialize for #ident #ty_generics #bounded_where_clause {
+                fn begin(#__out: &mut ::core::option::Option<Self>) -> &mut dyn #__miniserde::de::Visitor {
                     unsafe {
                         &mut *{
-                            __out as *mut #ex::Option<Self>
-                                as *mut __Visitor
+                            #__out as *mut ::core::option::Option<Self>
+                                as *mut #__Visitor #ty_generics
                         }
                     }
                 }
             }
 
-            impl miniserde::de::Visitor for __Visitor {
-                fn seq(&mut self) -> miniserde::Result<#ex::Box<dyn miniserde::de::Seq + '_>> {
-                    Ok(#ex::Box::new(__State {
-                        #(#field_idents: None,)*
-                        __state: 0,
-                        __out: &mut self.__out,
+            #[automatically_derived]
+            impl #impl_generics #__miniserde::de::Visitor for #__Visitor #ty_generics #bounded_where_clause {
+                fn seq(&mut self) -> #__miniserde::Result<#__alloc::boxed::Box<dyn #__miniserde::de::Seq + '_>> {
+                    ::core::result::Result::Ok(#__alloc::boxed::Box::new(#__State {
+                        #(#field_idents: ::core::option::Option::None,)*
+                        #__state: 0,
+                        #__out: &mut self.#__out,
                     }))
                 }
             }
 
-            struct __State<'a> {
-                #(#field_idents: #ex::Option<#field_types>,)*
-                __state: usize,
-                __out: &'a mut #ex::Option<#ident>,
+            struct #__State #wrapper_impl_generics #where_clause {
+                #(#field_idents: ::core::option::Option<#field_types>,)*
+                #__state: ::core::primitive::usize,
+                #__out: &#__a mut ::core::option::Option<#ident #ty_generics>,
             }
 
-            impl<'a> miniserde::de::Seq for __State<'a> {
-                fn element(&mut self) -> miniserde::Result<&mut dyn miniserde::de::Visitor> {
-                    let state = self.__state;
-                    self.__state += 1;
-                    match state {
-                        #(#index => Ok(<#field_types as miniserde::Deserialize>::begin(&mut self.#field_idents)),)*
-                        _ => Err(miniserde::Error),
+            #[automatically_derived]
+            impl #wrapper_impl_generics #__miniserde::de::Seq for #__State #wrapper_ty_generics #bounded_where_clause {
+                fn element(&mut self) -> #__miniserde::Result<&mut dyn #__miniserde::de::Visitor> {
+                    let #__current = self.#__state;
+                    self.#__state += 1;
+                    match #__current {
+                        #(#index => ::core::result::Result::Ok(#begin(&mut self.#field_idents)),)*
+                        _ => ::core::result::Result::Err(#__miniserde::Error),
                     }
                 }
 
-                fn finish(&mut self) -> miniserde::Result<()> {
-                    *self.__out = Some(#ident{
+                fn finish(&mut self) -> #__miniserde::Result<()> {
+                    *self.#__out = ::core::option::Option::Some(#ident{
                         #(#field_idents: match self.#field_idents.take() {
-                            Some(f) => f,
-                            None => return Err(miniserde::Error),
+                            ::core::option::Option::Some(#__value) => #__value,
+                            ::core::option::Option::None => return ::core::result::Result::Err(#__miniserde::Error),
                         },)*
+                        #__marker: ::core::marker::PhantomData,
                     });
-                    Ok(())
+                    ::core::result::Result::Ok(())
                 }
             }
         }
@@ -495,9 +1228,10 @@ pub fn unnamed_fields_as_struct(
     Ok(quote! {
         #as_struct
 
-        impl #ident {
-            fn as_enum(self) -> #enum_ident {
-                #enum_ident::#variant_ident(#(self.#field_idents,)*)
+        #[automatically_derived]
+        impl #impl_generics #ident #ty_generics #where_clause {
+            fn as_enum(self) -> #target {
+                #enum_path::#variant_ident(#(self.#field_idents,)*)
             }
         }
 
@@ -506,3 +1240,11 @@ pub fn unnamed_fields_as_struct(
         };
     })
 }
+
+/// The type of the marker field that lets the helper struct of a variant use
+/// every parameter of the enum.
+fn marker_type(input: &DeriveInput) -> TokenStream {
+    let ident = &input.ident;
+    let (_, ty_generics, _) = input.generics.split_for_impl();
+    quote!(::core::marker::PhantomData<#ident #ty_generics>)
+}
@@ -0,0 +1,90 @@
+use crate::array;
+use crate::attr::{self, DefaultValue, FieldAttrs, With};
+use crate::cow;
+use crate::hygiene;
+use syn::ext::IdentExt;
+use syn::{Field, Fields, Ident, Result, Variant};
+
+/// A field of a variant, as both derives see it.
+pub struct VariantField<'a> {
+    pub field: &'a Field,
+    /// The name of the field in patterns and in the generated helper types: its
+    /// own for a struct variant, a private one for a tuple variant.
+    pub ident: Ident,
+    /// The local the field is bound to when the enum is matched. It is private
+    /// so that it never shadows a path given in the attributes of the enum.
+    pub binding: Ident,
+    /// The attributes of the field. The elements of a tuple variant only have
+    /// with attributes, and are named after their index.
+    pub attrs: FieldAttrs,
+    /// The with functions of the field, including those standing in for the
+    /// impls miniserde lacks.
+    pub with: With,
+    /// Where the value of the field comes from when it is skipped or missing
+    /// from the input.
+    pub default: Option<DefaultValue>,
+}
+
+/// The fields of a variant along with their attributes, in declaration order.
+pub fn fields(variant: &Variant) -> Result<Vec<VariantField<'_>>> {
+    let variant_default = attr::variant_attrs(variant)?.default;
+    match &variant.fields {
+        Fields::Named(fields) => fields
+            .named
+            .iter()
+            .map(|field| {
+                let attrs = attr::field_attrs(field)?;
+                let default = match &attrs.default {
+                    Some(default) => Some(default.clone()),
+                    None if variant_default || attrs.skip_deserializing => {
+                        Some(DefaultValue::Trait)
+                    }
+                    None => None,
+                };
+                let ident = field.ident.clone().unwrap();
+                let binding = hygiene::ident(&format!("field_{}", ident.unraw()));
+                Ok(new(field, ident, binding, attrs, default))
+            })
+            .collect(),
+        Fields::Unnamed(fields) => Ok(fields
+            .unnamed
+            .iter()
+            .zip(attr::element_with(variant)?)
+            .enumerate()
+            .map(|(index, (field, with))| {
+                let attrs = FieldAttrs {
+                    name: index.to_string(),
+                    skip_serializing: false,
+                    skip_deserializing: false,
+                    default: None,
+                    skip_serializing_if: None,
+                    with,
+                    flatten: false,
+                };
+                let ident = hygiene::ident(&format!("f{}", index));
+                new(field, ident.clone(), ident, attrs, None)
+            })
+            .collect()),
+        Fields::Unit => Ok(Vec::new()),
+    }
+}
+
+fn new(
+    field: &Field,
+    ident: Ident,
+    binding: Ident,
+    attrs: FieldAttrs,
+    default: Option<DefaultValue>,
+) -> VariantField<'_> {
+    let mut with = attrs.with.clone();
+    array::default_with(&field.ty, &mut with);
+    cow::default_with(&field.ty, &mut with);
+    VariantField {
+        field,
+        ident,
+        binding,
+        attrs,
+        with,
+        default,
+    }
+}
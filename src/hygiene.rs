@@ -0,0 +1,58 @@
+use proc_macro2::{Span, TokenStream, TokenTree};
+use quote::ToTokens;
+use std::cell::Cell;
+use syn::ext::IdentExt;
+use syn::{DeriveInput, Ident, Lifetime};
+
+thread_local! {
+    static UNDERSCORES: Cell<usize> = const { Cell::new(2) };
+}
+
+/// Pick the prefix of the names of the generated code for the input: more
+/// leading underscores than any identifier it contains, including those in the
+/// string literals of its attributes. Generated names then never clash with
+/// the fields, variants, parameters or types of the enum.
+pub fn prepare(input: &DeriveInput) {
+    let underscores = max_underscores(input.to_token_stream()) + 1;
+    UNDERSCORES.with(|cell| cell.set(underscores.max(2)));
+}
+
+fn max_underscores(tokens: TokenStream) -> usize {
+    let leading = |word: &str| word.len() - word.trim_start_matches('_').len();
+    let mut max = 0;
+    for token in tokens {
+        let count = match token {
+            TokenTree::Group(group) => max_underscores(group.stream()),
+            TokenTree::Ident(ident) => leading(&ident.unraw().to_string()),
+            TokenTree::Literal(literal) => literal
+                .to_string()
+                .split(|c: char| !c.is_alphanumeric() && c != '_')
+                .map(leading)
+                .max()
+                .unwrap_or(0),
+            TokenTree::Punct(_) => 0,
+        };
+        max = max.max(count);
+    }
+    max
+}
+
+/// The identifier the generated code uses for `name`, whose leading
+/// underscores are replaced with the prefix.
+pub fn ident(name: &str) -> Ident {
+    let underscores = UNDERSCORES.with(Cell::get);
+    let name = format!(
+        "{}{}",
+        "_".repeat(underscores),
+        name.trim_start_matches('_')
+    );
+    Ident::new(&name, Span::call_site())
+}
+
+/// The lifetime the generated code uses for `'name`.
+pub fn lifetime(name: &str) -> Lifetime {
+    Lifetime {
+        apostrophe: Span::call_site(),
+        ident: ident(name),
+    }
+}
@@ -1,10 +1,32 @@
 extern crate proc_macro;
 
+/// Bind each `__name` to the identifier the generated code uses for it, and
+/// each lifetime `__name` after the semicolon to the lifetime `'__name`, to be
+/// interpolated in place of the bare names. See `hygiene`.
+macro_rules! private {
+    ($($name:ident),* $(; $($lifetime:ident),*)?) => {
+        $(
+            #[allow(non_snake_case)]
+            let $name = crate::hygiene::ident(stringify!($name));
+        )*
+        $($(
+            let $lifetime = crate::hygiene::lifetime(stringify!($lifetime));
+        )*)?
+    };
+}
+
+mod array;
 pub(crate) mod attr;
 mod bound;
+mod cow;
 mod de;
+mod field;
+mod hygiene;
 mod ser;
 
+use attr::ContainerAttrs;
+use proc_macro2::TokenStream;
+use quote::quote;
 use std::convert::From;
 use syn::{parse_macro_input, Data, DeriveInput, Error};
 
@@ -16,9 +38,36 @@ enum TagType {
     Adjacent { tag: String, content: String },
 }
 
+/// The items giving the generated code access to miniserde as `__miniserde`,
+/// either the crate itself or the path set with the crate attribute, and to the
+/// `alloc` crate as `__alloc`, both under their hygienic names.
+fn miniserde_alias(attrs: &ContainerAttrs) -> TokenStream {
+    private!(__alloc, __miniserde);
+    let miniserde = match &attrs.crate_path {
+        Some(path) => quote!(use #path as #__miniserde;),
+        None => quote!(
+            use ::miniserde as #__miniserde;
+        ),
+    };
+    quote! {
+        #miniserde
+        extern crate alloc as #__alloc;
+    }
+}
+
+/// The lint attribute of the generated items. They use deprecated variants and
+/// fields, name helper fields after the fields of the input, and spell out the
+/// paths of variants that a glob import may already have brought into scope.
+fn allowed_lints() -> TokenStream {
+    quote! {
+        #[allow(deprecated, non_snake_case, unused_qualifications)]
+    }
+}
+
 #[proc_macro_derive(Serialize_enum, attributes(serde))]
 pub fn derive_serialize(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let input = parse_macro_input!(tokens as DeriveInput);
+    hygiene::prepare(&input);
     let en = match &input.data {
         Data::Enum(en) => en,
         _ => {
@@ -27,7 +76,7 @@ pub fn derive_serialize(tokens: proc_macro::TokenStream) -> proc_macro::TokenStr
                 .into()
         }
     };
-    ser::derive(&input, &en)
+    ser::derive(&input, en)
         .unwrap_or_else(|err| err.to_compile_error())
         .into()
 }
@@ -35,15 +84,16 @@ pub fn derive_serialize(tokens: proc_macro::TokenStream) -> proc_macro::TokenStr
 #[proc_macro_derive(Deserialize_enum, attributes(serde))]
 pub fn derive_deserialize(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let input = parse_macro_input!(tokens as DeriveInput);
+    hygiene::prepare(&input);
     let en = match &input.data {
         Data::Enum(en) => en,
         _ => {
-            return Error::new_spanned(input, "Serialize_enum can only be applied to enums")
+            return Error::new_spanned(input, "Deserialize_enum can only be applied to enums")
                 .to_compile_error()
                 .into()
         }
     };
-    de::derive(&input, &en)
+    de::derive(&input, en)
         .unwrap_or_else(|err| err.to_compile_error())
         .into()
 }
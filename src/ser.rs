@@ -1,55 +1,92 @@
-use crate::attr;
+use crate::array;
+use crate::attr::{self, ContainerAttrs, SkipSerializing};
 use crate::bound;
+use crate::field::{self, VariantField};
 use crate::TagType;
-use proc_macro2::{Span, TokenStream};
+use proc_macro2::TokenStream;
 use quote::quote;
-use syn::{parse_quote, DataEnum, DeriveInput, Fields, FieldsNamed, FieldsUnnamed, Ident, Result};
+use syn::ext::IdentExt;
+use syn::{
+    parse_quote, DataEnum, DeriveInput, ExprPath, Fields, Result, Type, Variant, WhereClause,
+};
 
 pub fn derive(input: &DeriveInput, enumeration: &DataEnum) -> Result<TokenStream> {
-    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    private!(__miniserde, __value);
+    let (impl_generics, ty_generics, _) = input.generics.split_for_impl();
     let ident = &input.ident;
-    let tag_type = attr::tag_type(&input.attrs, &enumeration)?;
-    let names = enumeration
+    let attrs = attr::container_attrs(&input.attrs, enumeration)?;
+    if let Some(into) = &attrs.into {
+        return Ok(serialize_into(input, &attrs, into));
+    }
+    let enum_path = attrs.enum_path(ident);
+    let tag_type = &attrs.tag_type;
+    let bounded_where_clause = bound::where_clause_for_enum(
+        &input.generics,
+        enumeration,
+        &attrs.bound,
+        |bound| &bound.serialize,
+        serialized_types,
+    )?;
+    let variant_attrs = enumeration
         .variants
         .iter()
-        .map(attr::name_of_variant)
+        .map(attr::variant_attrs)
         .collect::<Result<Vec<_>>>()?;
     let begin = enumeration
         .variants
         .iter()
-        .zip(names.iter())
-        .map(|(variant, name)| {
+        .zip(variant_attrs.iter())
+        .map(|(variant, attrs)| {
             let var_ident = &variant.ident;
+            let name = &attrs.name;
+            match &attrs.skip_serializing {
+                Some(SkipSerializing::Fail) => {
+                    let message = format!(
+                        "the enum variant {}::{} cannot be serialized",
+                        ident, var_ident
+                    );
+                    return Ok(quote! {
+                        #enum_path::#var_ident { .. } => ::core::panic!(#message),
+                    });
+                }
+                Some(SkipSerializing::Placeholder(placeholder)) => {
+                    let implementation = serialize_unit(placeholder, tag_type)?;
+                    return Ok(quote! {
+                        #enum_path::#var_ident { .. } => {#implementation}
+                    });
+                }
+                None => {}
+            }
             Ok(match &variant.fields {
                 Fields::Unit => {
-                    let implementation = serialize_unit(name, &tag_type)?;
+                    let implementation = serialize_unit(name, tag_type)?;
                     quote! {
-                        #ident::#var_ident => {#implementation}
+                        #enum_path::#var_ident => {#implementation}
                     }
                 }
-                Fields::Named(fields) => {
-                    let implementation = serialize_named(input, &fields, name, &tag_type)?;
-                    let field_ident = fields
-                        .named
-                        .iter()
-                        .map(|field| &field.ident)
+                Fields::Named(_) => {
+                    let fields = field::fields(variant)?
+                        .into_iter()
+                        .filter(|field| !field.attrs.skip_serializing)
                         .collect::<Vec<_>>();
+                    let implementation =
+                        serialize_named(input, &fields, name, tag_type, &bounded_where_clause)?;
+                    let field_ident = fields.iter().map(|field| &field.ident);
+                    let binding = fields.iter().map(|field| &field.binding);
 
                     quote! {
-                        #ident::#var_ident{#(#field_ident),*} => {
+                        #enum_path::#var_ident{#(#field_ident: #binding,)* ..} => {
                             #implementation
                         }
                     }
                 }
-                Fields::Unnamed(fields) => {
-                    let field_ident = (0..fields.unnamed.len())
-                        .map(|i| format!("f{}", i))
-                        .map(|id| Ident::new(&id, Span::call_site()))
-                        .collect::<Vec<_>>();
+                Fields::Unnamed(_) => {
+                    let fields = field::fields(variant)?;
                     let implementation =
-                        serialize_unnamed(input, fields, &field_ident, name, &tag_type)?;
+                        serialize_unnamed(input, &fields, name, tag_type, &bounded_where_clause)?;
+                    let binding = fields.iter().map(|field| &field.binding);
                     quote! {
-                        #ident::#var_ident(#(#field_ident),*) => {
+                        #enum_path::#var_ident(#(#binding),*) => {
                             #implementation
                         }
                     }
@@ -58,140 +95,397 @@ pub fn derive(input: &DeriveInput, enumeration: &DataEnum) -> Result<TokenStream
         })
         .collect::<Result<Vec<_>>>()?;
 
-    Ok(quote! {
-        const _: () = {
-            impl #impl_generics miniserde::Serialize for #ident #ty_generics #where_clause {
-                fn begin(&self) -> miniserde::ser::Fragment {
+    // A remote enum gets a function usable with `with` instead of an impl.
+    let implementation = match &attrs.remote {
+        Some(remote) => {
+            let vis = &input.vis;
+            let doc = format!(
+                "Serializes a `{}` as defined by `{}`.",
+                quote!(#remote).to_string().replace(' ', ""),
+                ident.unraw(),
+            );
+            quote! {
+                #[automatically_derived]
+                impl #impl_generics #ident #ty_generics #bounded_where_clause {
+                    #[doc = #doc]
+                    #vis fn serialize(#__value: &#remote) -> #__miniserde::ser::Fragment {
+                        match #__value {
+                            #(#begin)*
+                        }
+                    }
+                }
+            }
+        }
+        None => quote! {
+            #[automatically_derived]
+            impl #impl_generics #__miniserde::Serialize for #ident #ty_generics #bounded_where_clause {
+                fn begin(&self) -> #__miniserde::ser::Fragment {
                     match self {
                         #(#begin)*
                     }
                 }
             }
+        },
+    };
+
+    let alias = crate::miniserde_alias(&attrs);
+    let allow = crate::allowed_lints();
+    let array_helper = array::serialize_helper(enumeration);
+    Ok(quote! {
+        #allow
+        const _: () = {
+            #alias
+            #array_helper
+            #implementation
         };
     })
 }
 
+/// Serialize the enum as the value a clone of it is converted into.
+fn serialize_into(input: &DeriveInput, attrs: &ContainerAttrs, into: &Type) -> TokenStream {
+    private!(
+        __alloc, __fragment, __IntoMap, __IntoSeq, __map, __miniserde, __seq, __string, __value;
+        __b
+    );
+    let ident = &input.ident;
+    let alias = crate::miniserde_alias(attrs);
+    let allow = crate::allowed_lints();
+    let (impl_generics, ty_generics, _) = input.generics.split_for_impl();
+    let mut generics = input.generics.clone();
+    let where_clause = generics.make_where_clause();
+    where_clause.predicates.push(
+        parse_quote!(#ident #ty_generics: ::core::clone::Clone + ::core::convert::Into<#into>),
+    );
+    where_clause
+        .predicates
+        .push(parse_quote!(#into: #__miniserde::Serialize));
+
+    quote! {
+        #allow
+        const _: () = {
+            #alias
+            // The fragments of the converted value borrow it, so it is kept
+            // alongside them as a raw pointer, which unlike a Box may be moved
+            // while borrowed, and freed after them.
+            struct #__IntoSeq<#__b, __T> {
+                seq: ::core::mem::ManuallyDrop<#__alloc::boxed::Box<dyn #__miniserde::ser::Seq + #__b>>,
+                value: *mut __T,
+            }
+
+            #[automatically_derived]
+            impl<#__b, __T> ::core::ops::Drop for #__IntoSeq<#__b, __T> {
+                fn drop(&mut self) {
+                    unsafe {
+                        ::core::mem::ManuallyDrop::drop(&mut self.seq);
+                        ::core::mem::drop(#__alloc::boxed::Box::from_raw(self.value));
+                    }
+                }
+            }
+
+            #[automatically_derived]
+            impl<#__b, __T> #__miniserde::ser::Seq for #__IntoSeq<#__b, __T> {
+                fn next(&mut self) -> ::core::option::Option<&dyn #__miniserde::Serialize> {
+                    self.seq.next()
+                }
+            }
+
+            struct #__IntoMap<#__b, __T> {
+                map: ::core::mem::ManuallyDrop<#__alloc::boxed::Box<dyn #__miniserde::ser::Map + #__b>>,
+                value: *mut __T,
+            }
+
+            #[automatically_derived]
+            impl<#__b, __T> ::core::ops::Drop for #__IntoMap<#__b, __T> {
+                fn drop(&mut self) {
+                    unsafe {
+                        ::core::mem::ManuallyDrop::drop(&mut self.map);
+                        ::core::mem::drop(#__alloc::boxed::Box::from_raw(self.value));
+                    }
+                }
+            }
+
+            #[automatically_derived]
+            impl<#__b, __T> #__miniserde::ser::Map for #__IntoMap<#__b, __T> {
+                fn next(&mut self) -> ::core::option::Option<(#__alloc::borrow::Cow<::core::primitive::str>, &dyn #__miniserde::Serialize)> {
+                    self.map.next()
+                }
+            }
+
+            #[automatically_derived]
+            impl #impl_generics #__miniserde::Serialize for #ident #ty_generics #where_clause {
+                fn begin(&self) -> #__miniserde::ser::Fragment {
+                    let #__value: *mut #into = #__alloc::boxed::Box::into_raw(
+                        #__alloc::boxed::Box::new(::core::convert::Into::into(::core::clone::Clone::clone(self))),
+                    );
+                    match #__miniserde::Serialize::begin(unsafe { &*#__value }) {
+                        #__miniserde::ser::Fragment::Seq(#__seq) => #__miniserde::ser::Fragment::Seq(
+                            #__alloc::boxed::Box::new(#__IntoSeq {
+                                seq: ::core::mem::ManuallyDrop::new(#__seq),
+                                value: #__value,
+                            }),
+                        ),
+                        #__miniserde::ser::Fragment::Map(#__map) => #__miniserde::ser::Fragment::Map(
+                            #__alloc::boxed::Box::new(#__IntoMap {
+                                map: ::core::mem::ManuallyDrop::new(#__map),
+                                value: #__value,
+                            }),
+                        ),
+                        #__fragment => {
+                            let #__fragment = match #__fragment {
+                                #__miniserde::ser::Fragment::Str(#__string) => #__miniserde::ser::Fragment::Str(
+                                    #__alloc::borrow::Cow::Owned(#__string.into_owned()),
+                                ),
+                                #__fragment => #__fragment,
+                            };
+                            ::core::mem::drop(unsafe { #__alloc::boxed::Box::from_raw(#__value) });
+                            #__fragment
+                        }
+                    }
+                }
+            }
+        };
+    }
+}
+
 fn serialize_unit(variant_name: &str, tag_type: &TagType) -> Result<TokenStream> {
+    private!(__alloc, __current, __Map, __miniserde, __state);
     Ok(if let TagType::Internal(tag) = &tag_type {
         quote! {
-            struct __Map {
-                state: miniserde::export::usize,
+            struct #__Map {
+                #__state: ::core::primitive::usize,
             }
 
-            impl miniserde::ser::Map for __Map {
-                fn next(&mut self) -> miniserde::export::Option<(miniserde::export::Cow<miniserde::export::str>, &dyn miniserde::Serialize)> {
-                    let __state = self.state;
-                    self.state = __state + 1;
-                    match __state {
-                        0 => miniserde::export::Some((
-                            miniserde::export::Cow::Borrowed(#tag),
+            #[automatically_derived]
+            impl #__miniserde::ser::Map for #__Map {
+                fn next(&mut self) -> ::core::option::Option<(#__alloc::borrow::Cow<::core::primitive::str>, &dyn #__miniserde::Serialize)> {
+                    let #__current = self.#__state;
+                    self.#__state = #__current + 1;
+                    match #__current {
+                        0 => ::core::option::Option::Some((
+                            #__alloc::borrow::Cow::Borrowed(#tag),
                             &#variant_name,
                         )),
-                        _ => miniserde::export::None,
+                        _ => ::core::option::Option::None,
                     }
                 }
             }
 
-            miniserde::ser::Fragment::Map(miniserde::export::Box::new(__Map {state: 0}))
+            #__miniserde::ser::Fragment::Map(#__alloc::boxed::Box::new(#__Map {#__state: 0}))
         }
     } else {
-        quote! {miniserde::ser::Fragment::Str(miniserde::export::Cow::Borrowed(#variant_name))}
+        quote! {#__miniserde::ser::Fragment::Str(#__alloc::borrow::Cow::Borrowed(#variant_name))}
     })
 }
 
+/// The types of the fields of a variant that are serialized through their
+/// Serialize impl, along with that trait.
+fn serialized_types(variant: &Variant) -> Result<Vec<(&Type, TokenStream)>> {
+    private!(__miniserde);
+    if attr::variant_attrs(variant)?.skip_serializing.is_some() {
+        return Ok(Vec::new());
+    }
+    let bound = quote!(#__miniserde::Serialize);
+    let types = field::fields(variant)?
+        .into_iter()
+        .filter(|field| !field.attrs.skip_serializing && field.attrs.with.serialize.is_none())
+        .map(|field| (&field.field.ty, bound.clone()))
+        .collect();
+    Ok(types)
+}
+
 fn serialize_named(
     input: &DeriveInput,
-    fields: &FieldsNamed,
+    fields: &[VariantField],
     variant_name: &str,
     tag_type: &TagType,
+    bounded_where_clause: &WhereClause,
 ) -> Result<TokenStream> {
-    let field_ident = fields
-        .named
-        .iter()
-        .map(|field| &field.ident)
-        .collect::<Vec<_>>();
-    let field_name = fields
-        .named
-        .iter()
-        .map(attr::name_of_field)
-        .collect::<Result<Vec<_>>>()?;
+    private!(
+        __alloc,
+        __AsStruct,
+        __current,
+        __data,
+        __entry,
+        __map,
+        __Map,
+        __marker,
+        __miniserde,
+        __state,
+        __SuperMap;
+        __b
+    );
+    let field_ident = fields.iter().map(|field| &field.ident).collect::<Vec<_>>();
     let field_type = fields
-        .named
         .iter()
-        .map(|field| &field.ty)
+        .map(|field| &field.field.ty)
         .collect::<Vec<_>>();
     let (_, _, where_clause) = input.generics.split_for_impl();
-    let wrapper_generics = bound::with_lifetime_bound(&input.generics, "'__b");
+    let wrapper_generics = bound::with_lifetime_bound(&input.generics, &__b);
     let (wrapper_impl_generics, wrapper_ty_generics, _) = wrapper_generics.split_for_impl();
-    let bound = parse_quote!(miniserde::Serialize);
-    let bounded_where_clause = bound::where_clause_with_bound(&input.generics, bound);
-    let cow = quote!(miniserde::export::Cow);
-    let some = quote!(miniserde::export::Some);
+    let marker = marker_type(input);
+    let turbofish = bound::turbofish(&input.generics);
+    let entry = fields.iter().zip(1usize..).map(|(field, index)| {
+        let ident = &field.ident;
+        let attrs = &field.attrs;
+        let name = &attrs.name;
+        if attrs.flatten {
+            // Stay in this state until the flattened map is exhausted.
+            return quote! {
+                if self.#__state == #index {
+                    if let ::core::option::Option::Some(#__map) = &mut self.#ident {
+                        if let ::core::option::Option::Some(#__entry) = #__map.next() {
+                            return ::core::option::Option::Some(#__entry);
+                        }
+                    }
+                    self.#__state = #index + 1;
+                }
+            };
+        }
+        let (value, borrowed) = match &field.with.serialize {
+            Some(_) => (quote!(&self.#ident), quote!(self.#ident.value)),
+            None => (quote!(self.#ident), quote!(self.#ident)),
+        };
+        let mut entry = quote!(return ::core::option::Option::Some((#__alloc::borrow::Cow::Borrowed(#name), #value)););
+        if let Some(path) = &attrs.skip_serializing_if {
+            entry = quote!(if !#path(#borrowed) { #entry });
+        }
+        quote! {
+            if self.#__state == #index {
+                self.#__state = #index + 1;
+                #entry
+            }
+        }
+    });
+    let serialize_with = fields
+        .iter()
+        .filter(|field| !field.attrs.flatten)
+        .map(|field| &field.with.serialize);
+    let wrapper = serialize_with_wrapper(serialize_with);
+    let map_type = fields.iter().map(|field| {
+        if field.attrs.flatten {
+            quote!(::core::option::Option<#__alloc::boxed::Box<dyn #__miniserde::ser::Map + #__b>>)
+        } else {
+            borrowed_type(&field.field.ty, &field.with.serialize)
+        }
+    });
+    // The values come from the fields of the struct for an externally tagged
+    // variant, and from the bindings of the enum's fields otherwise.
+    let map_init = |values: Vec<TokenStream>| {
+        fields
+            .iter()
+            .zip(values)
+            .map(|(field, value)| {
+                if !field.attrs.flatten {
+                    return borrowed_value(value, &field.with.serialize);
+                }
+                let fragment = match &field.with.serialize {
+                    Some(path) => quote!(#path(#value)),
+                    None => quote!(#__miniserde::Serialize::begin(#value)),
+                };
+                quote! {
+                    match #fragment {
+                        #__miniserde::ser::Fragment::Map(#__map) => ::core::option::Option::Some(#__map),
+                        #__miniserde::ser::Fragment::Null => ::core::option::Option::None,
+                        _ => ::core::panic!("can only flatten structs and maps"),
+                    }
+                }
+            })
+            .collect::<Vec<_>>()
+    };
+    let external_init = map_init(
+        field_ident
+            .iter()
+            .map(|ident| quote!(self.#ident))
+            .collect(),
+    );
+    let binding = fields
+        .iter()
+        .map(|field| &field.binding)
+        .collect::<Vec<_>>();
+    let init = map_init(binding.iter().map(|binding| quote!(#binding)).collect());
+    let (start, tag_entry) = if let TagType::Internal(ref tag) = &tag_type {
+        let entry = quote! {
+            if self.#__state == 0 {
+                self.#__state = 1;
+                return ::core::option::Option::Some((#__alloc::borrow::Cow::Borrowed(#tag), &#variant_name));
+            }
+        };
+        (0, entry)
+    } else {
+        (1usize, quote!())
+    };
+    let map = quote! {
+        #wrapper
+
+        struct #__Map #wrapper_impl_generics #where_clause {
+            #(#field_ident: #map_type,)*
+            #__marker: #marker,
+            #__state: ::core::primitive::usize,
+        }
+
+        #[automatically_derived]
+        impl #wrapper_impl_generics #__miniserde::ser::Map for #__Map #wrapper_ty_generics #bounded_where_clause {
+            fn next(&mut self) -> ::core::option::Option<(#__alloc::borrow::Cow<::core::primitive::str>, &dyn #__miniserde::Serialize)> {
+                // Each entry falls through to the next one when it is skipped.
+                #tag_entry
+                #(#entry)*
+                ::core::option::Option::None
+            }
+        }
+    };
     if let TagType::External = tag_type {
         Ok(quote! {
-            use miniserde::Serialize;
-            #[derive(Serialize)]
-            struct __AsStruct #wrapper_impl_generics #where_clause {
-                #(#field_ident: &'__b #field_type,)*
+            #map
+
+            struct #__AsStruct #wrapper_impl_generics #where_clause {
+                #(#field_ident: &#__b #field_type,)*
+                #__marker: #marker,
+            }
+
+            #[automatically_derived]
+            impl #wrapper_impl_generics #__miniserde::Serialize for #__AsStruct #wrapper_ty_generics #bounded_where_clause {
+                fn begin(&self) -> #__miniserde::ser::Fragment {
+                    #__miniserde::ser::Fragment::Map(#__alloc::boxed::Box::new(#__Map #turbofish {
+                        #(#field_ident: #external_init,)*
+                        #__marker: ::core::marker::PhantomData,
+                        #__state: 1,
+                    }))
+                }
             }
 
-            struct __SuperMap #wrapper_impl_generics #where_clause {
-                data: __AsStruct #wrapper_ty_generics,
-                state: miniserde::export::usize,
+            struct #__SuperMap #wrapper_impl_generics #where_clause {
+                #__data: #__AsStruct #wrapper_ty_generics,
+                #__state: ::core::primitive::usize,
             }
 
-            impl #wrapper_impl_generics miniserde::ser::Map for __SuperMap #wrapper_ty_generics #bounded_where_clause {
-                fn next(&mut self) -> miniserde::export::Option<(#cow<miniserde::export::str>, &dyn miniserde::Serialize)> {
-                    let __state = self.state;
-                    self.state = __state + 1;
-                    match __state {
-                        0 => #some((#cow::Borrowed(#variant_name), &self.data)),
-                        _ => miniserde::export::None,
+            #[automatically_derived]
+            impl #wrapper_impl_generics #__miniserde::ser::Map for #__SuperMap #wrapper_ty_generics #bounded_where_clause {
+                fn next(&mut self) -> ::core::option::Option<(#__alloc::borrow::Cow<::core::primitive::str>, &dyn #__miniserde::Serialize)> {
+                    let #__current = self.#__state;
+                    self.#__state = #__current + 1;
+                    match #__current {
+                        0 => ::core::option::Option::Some((#__alloc::borrow::Cow::Borrowed(#variant_name), &self.#__data)),
+                        _ => ::core::option::Option::None,
                     }
                 }
             }
 
-            miniserde::ser::Fragment::Map(miniserde::export::Box::new(__SuperMap {
-                data: __AsStruct { #(#field_ident),* },
-                state: 0,
+            #__miniserde::ser::Fragment::Map(#__alloc::boxed::Box::new(#__SuperMap {
+                #__data: #__AsStruct #turbofish {
+                    #(#field_ident: #binding,)*
+                    #__marker: ::core::marker::PhantomData,
+                },
+                #__state: 0,
             }))
         })
     } else {
-        let (start, tag_arm) = if let TagType::Internal(ref tag) = &tag_type {
-            (
-                0,
-                quote! {0 => #some((#cow::Borrowed(#tag), &#variant_name)),},
-            )
-        } else {
-            (1usize, quote!())
-        };
-        let index = 1usize..;
         Ok(quote! {
-            struct __Map #wrapper_impl_generics {
-                #(#field_ident: &'__b #field_type),*,
-                state: miniserde::export::usize,
-            }
-
-            impl #wrapper_impl_generics miniserde::ser::Map for __Map #wrapper_ty_generics #where_clause {
-                fn next(&mut self) -> miniserde::export::Option<(#cow<miniserde::export::str>, &dyn miniserde::Serialize)> {
-                    let __state = self.state;
-                    self.state = __state + 1;
-                    match __state {
-                        #tag_arm
-                        #(#index => {
-                            #some((
-                                    #cow::Borrowed(#field_name),
-                                    self.#field_ident,
-                                    ))
-                        })*,
-                        _ => miniserde::export::None,
-                    }
-                }
-            }
+            #map
 
-            miniserde::ser::Fragment::Map(miniserde::export::Box::new(__Map {
-                #(#field_ident),*,
-                state: #start,
+            #__miniserde::ser::Fragment::Map(#__alloc::boxed::Box::new(#__Map #turbofish {
+                #(#field_ident: #init,)*
+                #__marker: ::core::marker::PhantomData,
+                #__state: #start,
             }))
         })
     }
@@ -200,78 +494,117 @@ fn serialize_named(
 #[allow(clippy::cognitive_complexity)]
 fn serialize_unnamed(
     input: &DeriveInput,
-    fields: &FieldsUnnamed,
-    field_ident: &[Ident],
+    fields: &[VariantField],
     variant_name: &str,
     tag_type: &TagType,
+    bounded_where_clause: &WhereClause,
 ) -> Result<TokenStream> {
+    private!(
+        __alloc,
+        __AsStruct,
+        __current,
+        __data,
+        __marker,
+        __miniserde,
+        __Seq,
+        __state,
+        __SuperMap;
+        __b
+    );
+    let field_ident = fields.iter().map(|field| &field.ident).collect::<Vec<_>>();
     let field_type = fields
-        .unnamed
         .iter()
-        .map(|field| &field.ty)
+        .map(|field| &field.field.ty)
         .collect::<Vec<_>>();
+    let serialize_with = fields.iter().map(|field| &field.with.serialize);
     let (_, _, where_clause) = input.generics.split_for_impl();
-    let wrapper_generics = bound::with_lifetime_bound(&input.generics, "'__b");
+    let wrapper_generics = bound::with_lifetime_bound(&input.generics, &__b);
     let (wrapper_impl_generics, wrapper_ty_generics, _) = wrapper_generics.split_for_impl();
-    let bound = parse_quote!(miniserde::Serialize);
-    let bounded_where_clause = bound::where_clause_with_bound(&input.generics, bound);
+    let marker = marker_type(input);
+    let turbofish = bound::turbofish(&input.generics);
     let index = 0usize..;
-    let ex = quote!(miniserde::export);
     let seq = if field_ident.len() == 1 {
-        quote! { #(#field_ident.begin())* }
+        let ident = field_ident[0];
+        match &fields[0].with.serialize {
+            Some(path) => quote! { #path(#ident) },
+            None => quote! { #__miniserde::Serialize::begin(#ident) },
+        }
     } else {
+        let wrapper = serialize_with_wrapper(serialize_with.clone());
+        let seq_type = serialize_with
+            .clone()
+            .zip(&field_type)
+            .map(|(with, ty)| borrowed_type(ty, with));
+        let seq_init = serialize_with
+            .clone()
+            .zip(&field_ident)
+            .map(|(with, ident)| borrowed_value(quote!(#ident), with));
+        let element = serialize_with
+            .clone()
+            .zip(&field_ident)
+            .map(|(with, ident)| match with {
+                Some(_) => quote!(&self.#ident),
+                None => quote!(self.#ident),
+            });
         quote! {
-            struct __Seq #wrapper_impl_generics #where_clause {
-                #(#field_ident: &'__b #field_type),*,
-                state: miniserde::export::usize,
+            #wrapper
+
+            struct #__Seq #wrapper_impl_generics #where_clause {
+                #(#field_ident: #seq_type,)*
+                #__marker: #marker,
+                #__state: ::core::primitive::usize,
             }
 
-            impl #wrapper_impl_generics miniserde::ser::Seq for __Seq #wrapper_ty_generics #bounded_where_clause {
-                fn next(&mut self) -> #ex::Option<&dyn miniserde::Serialize> {
-                    let __state = self.state;
-                    self.state = __state + 1;
-                    match __state {
-                        #(#index => #ex::Some(self.#field_ident)),*,
-                        _ => #ex::None,
+            #[automatically_derived]
+            impl #wrapper_impl_generics #__miniserde::ser::Seq for #__Seq #wrapper_ty_generics #bounded_where_clause {
+                fn next(&mut self) -> ::core::option::Option<&dyn #__miniserde::Serialize> {
+                    let #__current = self.#__state;
+                    self.#__state = #__current + 1;
+                    match #__current {
+                        #(#index => ::core::option::Option::Some(#element)),*,
+                        _ => ::core::option::Option::None,
                     }
                 }
             }
 
-            miniserde::ser::Fragment::Seq(#ex::Box::new(__Seq {
-                #(#field_ident),*,
-                state: 0,
+            #__miniserde::ser::Fragment::Seq(#__alloc::boxed::Box::new(#__Seq #turbofish {
+                #(#field_ident: #seq_init,)*
+                #__marker: ::core::marker::PhantomData,
+                #__state: 0,
             }))
         }
     };
     Ok(if let TagType::External = tag_type {
         quote! {
-            struct __AsStruct #wrapper_impl_generics (#(&'__b #field_type),*) #where_clause;
+            struct #__AsStruct #wrapper_impl_generics (#(&#__b #field_type,)* #marker) #where_clause;
 
-            impl #wrapper_impl_generics miniserde::Serialize for __AsStruct #wrapper_ty_generics #bounded_where_clause {
-                fn begin(&self) -> miniserde::ser::Fragment {
-                    let __AsStruct(#(#field_ident),*) = self;
+            #[automatically_derived]
+            impl #wrapper_impl_generics #__miniserde::Serialize for #__AsStruct #wrapper_ty_generics #bounded_where_clause {
+                fn begin(&self) -> #__miniserde::ser::Fragment {
+                    let #__AsStruct(#(#field_ident,)* _) = *self;
                     #seq
                 }
             }
 
-            struct __SuperMap #wrapper_impl_generics #where_clause {
-                data: __AsStruct #wrapper_ty_generics,
-                state: bool,
+            struct #__SuperMap #wrapper_impl_generics #where_clause {
+                #__data: #__AsStruct #wrapper_ty_generics,
+                #__state: ::core::primitive::bool,
             }
 
-            impl #wrapper_impl_generics miniserde::ser::Map for __SuperMap #wrapper_ty_generics #bounded_where_clause {
-                fn next(&mut self) -> miniserde::export::Option<(#ex::Cow<miniserde::export::str>, &dyn miniserde::Serialize)> {
-                    if self.state {
-                        return miniserde::export::None;
+            #[automatically_derived]
+            impl #wrapper_impl_generics #__miniserde::ser::Map for #__SuperMap #wrapper_ty_generics #bounded_where_clause {
+                fn next(&mut self) -> ::core::option::Option<(#__alloc::borrow::Cow<::core::primitive::str>, &dyn #__miniserde::Serialize)> {
+                    if self.#__state {
+                        return ::core::option::Option::None;
                     }
-                    self.state = true;
-                    #ex::Some((#ex::Cow::Borrowed(#variant_name), &self.data))
+                    self.#__state = true;
+                    ::core::option::Option::Some((#__alloc::borrow::Cow::Borrowed(#variant_name), &self.#__data))
                 }
             }
 
-            miniserde::ser::Fragment::Map(#ex::Box::new(__SuperMap {
-                data: __AsStruct ( #(#field_ident),* ),
-                state: false,
+            #__miniserde::ser::Fragment::Map(#__alloc::boxed::Box::new(#__SuperMap {
+                #__data: #__AsStruct #turbofish (#(#field_ident,)* ::core::marker::PhantomData),
+                #__state: false,
             }))
         }
     } else {
@@ -280,3 +613,54 @@ fn serialize_unnamed(
         }
     })
 }
+
+/// The type of the marker field that lets the helper types of a variant use every
+/// generic parameter of the enum, whichever fields the variant has.
+fn marker_type(input: &DeriveInput) -> TokenStream {
+    private!(; __b);
+    let ident = &input.ident;
+    let (_, ty_generics, _) = input.generics.split_for_impl();
+    quote!(::core::marker::PhantomData<&#__b #ident #ty_generics>)
+}
+
+/// The definition of the wrapper that serializes a borrowed value through a
+/// serialize_with function, if any of the values needs it.
+fn serialize_with_wrapper<'a>(
+    mut serialize_with: impl Iterator<Item = &'a Option<ExprPath>>,
+) -> TokenStream {
+    private!(__miniserde, __SerializeWith; __b);
+    if !serialize_with.any(|with| with.is_some()) {
+        return quote!();
+    }
+    quote! {
+        struct #__SerializeWith<#__b, __T: ?Sized> {
+            value: &#__b __T,
+            serialize: fn(&__T) -> #__miniserde::ser::Fragment,
+        }
+
+        #[automatically_derived]
+        impl<#__b, __T: ?Sized> #__miniserde::Serialize for #__SerializeWith<#__b, __T> {
+            fn begin(&self) -> #__miniserde::ser::Fragment {
+                (self.serialize)(self.value)
+            }
+        }
+    }
+}
+
+/// The type under which a helper struct stores a borrowed value.
+fn borrowed_type(ty: &Type, serialize_with: &Option<ExprPath>) -> TokenStream {
+    private!(__SerializeWith; __b);
+    match serialize_with {
+        Some(_) => quote!(#__SerializeWith<#__b, #ty>),
+        None => quote!(&#__b #ty),
+    }
+}
+
+/// Wrap a borrowed value to store it in a helper struct.
+fn borrowed_value(value: TokenStream, serialize_with: &Option<ExprPath>) -> TokenStream {
+    private!(__SerializeWith);
+    match serialize_with {
+        Some(path) => quote!(#__SerializeWith { value: #value, serialize: #path }),
+        None => value,
+    }
+}
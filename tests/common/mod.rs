@@ -0,0 +1,411 @@
+//! Fixtures shared by the serialize and deserialize tests. Enums derive the
+//! traits of the directions that support them, and each test checks its own
+//! direction.
+
+pub fn seven() -> u8 {
+    7
+}
+
+pub mod u64_as_string {
+    use miniserde::de::Visitor;
+    use miniserde::ser::Fragment;
+    use miniserde::{make_place, Error, Result};
+    use std::borrow::Cow;
+
+    make_place!(Place);
+
+    impl Visitor for Place<u64> {
+        fn string(&mut self, s: &str) -> Result<()> {
+            self.out = Some(s.parse().map_err(|_| Error)?);
+            Ok(())
+        }
+    }
+
+    pub fn serialize(value: &u64) -> Fragment<'_> {
+        Fragment::Str(Cow::Owned(value.to_string()))
+    }
+
+    pub fn deserialize(out: &mut Option<u64>) -> &mut dyn Visitor {
+        Place::new(out)
+    }
+}
+
+/// Derived enums under the lint levels of a strict workspace.
+pub mod strict {
+    #![deny(warnings, clippy::pedantic, missing_docs, non_snake_case)]
+    // The derives of older miniserde versions emit their impls inside a named const.
+    #![allow(non_local_definitions)]
+
+    use miniserde::{Deserialize, Serialize};
+    use miniserde_enum::{Deserialize_enum, Serialize_enum};
+    use std::borrow::Cow;
+
+    /// A flattened header.
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    pub struct Header {
+        /// The id.
+        pub id: u32,
+    }
+
+    /// An externally tagged enum.
+    #[derive(Serialize_enum, Deserialize_enum, Debug, PartialEq)]
+    pub enum External<'a, T, const N: usize> {
+        /// A struct variant.
+        Named {
+            /// A generic field.
+            value: T,
+            /// An array.
+            array: [u8; N],
+            /// Text, owned once deserialized.
+            text: Cow<'a, str>,
+            /// Defaulted when missing, skipped when empty.
+            #[serde(default, skip_serializing_if = "Vec::is_empty")]
+            list: Vec<u8>,
+            /// Flattened.
+            #[serde(flatten)]
+            header: Header,
+            /// Never serialized or deserialized.
+            #[serde(skip)]
+            skipped: bool,
+        },
+        /// A tuple variant.
+        Tuple(T, [u8; N]),
+        /// A newtype variant.
+        Newtype(u64),
+        /// A unit variant.
+        Unit,
+    }
+
+    /// An internally tagged enum.
+    #[derive(Serialize_enum, Deserialize_enum, Debug, PartialEq)]
+    #[serde(tag = "type")]
+    pub enum Internal {
+        /// A struct variant.
+        Named {
+            /// A field.
+            x: i32,
+            /// Flattened.
+            #[serde(flatten)]
+            header: Header,
+        },
+        /// A unit variant.
+        Unit,
+    }
+
+    /// An adjacently tagged enum.
+    #[derive(Deserialize_enum, Debug, PartialEq)]
+    #[serde(tag = "t", content = "c")]
+    pub enum Adjacent {
+        /// A struct variant.
+        Named {
+            /// A field.
+            x: i32,
+        },
+        /// A tuple variant.
+        Tuple(i32, i32),
+        /// A unit variant.
+        Unit,
+    }
+
+    /// An untagged enum.
+    #[derive(Serialize_enum)]
+    #[serde(untagged)]
+    pub enum Untagged {
+        /// A struct variant.
+        Named {
+            /// A field.
+            x: i32,
+        },
+        /// A tuple variant.
+        Tuple(i32, i32),
+    }
+
+    /// An enum with the field names of the wire format.
+    #[derive(Serialize_enum, Deserialize_enum, Debug, PartialEq)]
+    #[allow(non_snake_case)]
+    pub enum Wire {
+        /// A struct variant.
+        Named {
+            /// A camel case field.
+            camelCase: u8,
+        },
+    }
+
+    /// An enum converted to and from another one.
+    #[derive(Serialize_enum, Deserialize_enum, Clone, Debug, PartialEq)]
+    #[serde(from = "Internal", into = "Internal")]
+    pub enum Converted {
+        /// A unit variant.
+        Unit,
+    }
+
+    impl From<Internal> for Converted {
+        fn from(_: Internal) -> Self {
+            Converted::Unit
+        }
+    }
+
+    impl From<Converted> for Internal {
+        fn from(_: Converted) -> Self {
+            Internal::Unit
+        }
+    }
+
+    /// The definition of a remote enum.
+    #[derive(Serialize_enum, Deserialize_enum)]
+    #[serde(remote = "std::cmp::Ordering")]
+    #[allow(dead_code)]
+    pub enum OrderingDef {
+        /// Less.
+        Less,
+        /// Equal.
+        Equal,
+        /// Greater.
+        Greater,
+    }
+}
+
+/// Enums and fields named like the items of the generated code.
+#[allow(non_camel_case_types, clippy::enum_variant_names)]
+pub mod adversarial_names {
+    use miniserde_enum::{Deserialize_enum, Serialize_enum};
+    use std::borrow::Cow;
+
+    pub type __Visitor = u8;
+    pub type __Map = u8;
+    pub mod __alloc {
+        pub type Text = String;
+    }
+
+    #[derive(Serialize_enum, Deserialize_enum, Debug, PartialEq)]
+    pub enum External<'__a, '__b, __T> {
+        __Visitor {
+            state: __Visitor,
+            data: __alloc::Text,
+            __out: __T,
+            __marker: Cow<'__a, str>,
+            __state: Cow<'__b, str>,
+            __seen: bool,
+        },
+        __State(__T, __Map),
+        __Map,
+        __AsStruct,
+    }
+
+    #[derive(Serialize_enum, Deserialize_enum, Debug, PartialEq)]
+    #[serde(tag = "type")]
+    pub enum Internal {
+        __Map { state: u8, data: u8, __state: u8 },
+        __State { __out: u8, __tag: u8, __seen: u8 },
+    }
+
+    #[derive(Deserialize_enum, Debug, PartialEq)]
+    #[serde(tag = "t", content = "c")]
+    pub enum Adjacent {
+        __Visitor { state: u8, __out: u8 },
+        __Seq(u8, u8),
+    }
+}
+
+/// Enums whose attributes name functions like the locals of the generated code.
+pub mod local_names {
+    use miniserde::de::Visitor;
+    use miniserde::ser::Fragment;
+    use miniserde::Deserialize;
+    use miniserde_enum::{Deserialize_enum, Serialize_enum};
+
+    pub fn k(out: &mut Option<u8>) -> &mut dyn Visitor {
+        u8::begin(out)
+    }
+
+    pub fn state(out: &mut Option<u8>) -> &mut dyn Visitor {
+        u8::begin(out)
+    }
+
+    pub fn hex(value: &u8) -> Fragment<'_> {
+        Fragment::Str(format!("{:02x}", value).into())
+    }
+
+    #[derive(Serialize_enum, Deserialize_enum, Debug, PartialEq)]
+    pub enum External {
+        Key {
+            #[serde(deserialize_with = "k")]
+            k: u8,
+        },
+        Tuple(u8, #[serde(deserialize_with = "state")] u8),
+    }
+
+    #[derive(Serialize_enum, Deserialize_enum, Debug, PartialEq)]
+    #[serde(tag = "type")]
+    pub enum Internal {
+        Hex {
+            #[serde(serialize_with = "hex")]
+            hex: u8,
+        },
+    }
+}
+
+macro_rules! with_fields {
+    ($(#[$attr:meta])* $name:ident) => {
+        $(#[$attr])*
+        pub enum $name {
+            C {
+                #[serde(rename = "y")]
+                x: u8,
+                #[serde(default, skip_serializing_if = "Vec::is_empty")]
+                list: Vec<u8>,
+                #[serde(default = "crate::common::seven")]
+                default: u8,
+                #[serde(skip)]
+                skipped: u8,
+                #[serde(skip_serializing)]
+                skipped_serializing: u8,
+                #[serde(skip_deserializing)]
+                skipped_deserializing: u8,
+                #[serde(with = "crate::common::u64_as_string")]
+                id: u64,
+            },
+        }
+
+        impl $name {
+            pub fn example() -> Self {
+                $name::C {
+                    x: 1,
+                    list: vec![],
+                    default: super::seven(),
+                    skipped: 0,
+                    skipped_serializing: 2,
+                    skipped_deserializing: 0,
+                    id: 4,
+                }
+            }
+        }
+    };
+}
+
+/// The same field attributes under each tagging.
+pub mod field_attributes {
+    use miniserde_enum::{Deserialize_enum, Serialize_enum};
+
+    with_fields!(
+        #[derive(Serialize_enum, Deserialize_enum, Debug, PartialEq)]
+        External
+    );
+    with_fields!(
+        #[derive(Serialize_enum, Deserialize_enum, Debug, PartialEq)]
+        #[serde(tag = "type")]
+        Internal
+    );
+    with_fields!(
+        #[derive(Deserialize_enum, Debug, PartialEq)]
+        #[serde(tag = "type", content = "content")]
+        Adjacent
+    );
+    with_fields!(
+        #[derive(Serialize_enum)]
+        #[serde(untagged)]
+        Untagged
+    );
+}
+
+pub mod sdk {
+    pub mod json {
+        pub use miniserde as mini;
+    }
+}
+
+/// An enum reaching miniserde through a re-export.
+pub mod crate_path {
+    use miniserde_enum::{Deserialize_enum, Serialize_enum};
+
+    #[derive(Serialize_enum, Deserialize_enum, Debug, PartialEq)]
+    #[serde(crate = "crate::common::sdk::json::mini")]
+    pub enum Event {
+        Start { at: u64 },
+        Stop(u64),
+        Reset,
+    }
+}
+
+/// Enums derived where the names of the prelude and of miniserde are shadowed.
+#[allow(dead_code, non_camel_case_types)]
+pub mod shadowed_prelude {
+    use miniserde_enum::{Deserialize_enum, Serialize_enum};
+
+    struct Option;
+    struct Some;
+    struct None;
+    struct Ok;
+    struct Err;
+    struct String;
+    struct Box;
+    struct Default;
+    struct Clone;
+    struct Into;
+    struct bool;
+    struct usize;
+    struct str;
+    struct Serialize;
+    struct Deserialize;
+    mod miniserde {}
+
+    #[derive(Serialize_enum, Deserialize_enum, Debug, PartialEq)]
+    pub enum External {
+        Unit,
+        Named {
+            at: u64,
+            #[serde(default)]
+            count: u64,
+        },
+        Newtype(u64),
+        Tuple(u64, u64),
+    }
+
+    #[derive(Serialize_enum, Deserialize_enum, Debug, PartialEq)]
+    #[serde(tag = "type")]
+    pub enum Internal {
+        Unit,
+        Named { at: u64 },
+    }
+
+    #[derive(Deserialize_enum, Debug, PartialEq)]
+    #[serde(tag = "type", content = "content")]
+    pub enum Adjacent {
+        Unit,
+        Named { at: u64 },
+    }
+}
+
+/// Only miniserde's documented API, without its hidden `export` module.
+pub mod public_api {
+    pub use miniserde::{de, ser, Deserialize, Error, Result, Serialize};
+}
+
+/// Enums reaching miniserde through `public_api`.
+pub mod public_api_only {
+    use miniserde_enum::{Deserialize_enum, Serialize_enum};
+
+    #[derive(Serialize_enum, Deserialize_enum, Debug, PartialEq)]
+    #[serde(crate = "crate::common::public_api")]
+    pub enum External {
+        A { x: i32, label: Option<String> },
+        B(i32, String),
+        C(bool),
+        D,
+    }
+
+    #[derive(Serialize_enum, Deserialize_enum, Debug, PartialEq)]
+    #[serde(crate = "crate::common::public_api", tag = "type")]
+    pub enum Internal {
+        A { x: i32 },
+        B,
+    }
+
+    #[derive(Deserialize_enum, Debug, PartialEq)]
+    #[serde(crate = "crate::common::public_api", tag = "t", content = "c")]
+    pub enum Adjacent {
+        A { x: i32 },
+        B(i32, i32),
+        C,
+    }
+}
@@ -1,3 +1,6 @@
+pub mod common;
+
+use common::u64_as_string;
 use miniserde::{json, Deserialize};
 use miniserde_enum::Deserialize_enum;
 
@@ -58,3 +61,938 @@ fn test_internal() {
     let expected = [B, C { x: 2 }, D];
     assert_eq!(actual, expected);
 }
+
+#[test]
+fn skipped_variants() {
+    #[derive(Deserialize_enum, Debug, PartialEq)]
+    #[allow(dead_code)]
+    enum Skipped {
+        A,
+        #[serde(skip_deserializing)]
+        B,
+        #[serde(skip)]
+        C(i32),
+        #[serde(skip_serializing)]
+        D {
+            x: i32,
+        },
+    }
+    use Skipped::*;
+    let actual: Vec<Skipped> = json::from_str(r#"["A",{"D":{"x":1}}]"#).unwrap();
+    assert_eq!(actual, [A, D { x: 1 }]);
+    assert!(json::from_str::<Skipped>(r#""B""#).is_err());
+    assert!(json::from_str::<Skipped>(r#"{"C":1}"#).is_err());
+}
+
+#[test]
+fn skipped_fields() {
+    #[derive(Deserialize_enum, Debug, PartialEq)]
+    #[serde(tag = "type")]
+    enum Internal {
+        A {
+            x: i32,
+            #[serde(skip_deserializing)]
+            hash: u64,
+            #[serde(skip)]
+            cached: Option<String>,
+        },
+    }
+    #[derive(Deserialize_enum, Debug, PartialEq)]
+    enum External {
+        A {
+            #[serde(skip)]
+            hash: u64,
+            #[serde(rename = "y")]
+            x: i32,
+        },
+    }
+    let actual: Internal = json::from_str(r#"{"type":"A","x":1,"hash":5}"#).unwrap();
+    let expected = Internal::A {
+        x: 1,
+        hash: 0,
+        cached: None,
+    };
+    assert_eq!(actual, expected);
+    let actual: External = json::from_str(r#"{"A":{"y":1}}"#).unwrap();
+    assert_eq!(actual, External::A { hash: 0, x: 1 });
+}
+
+fn default_port() -> u16 {
+    8080
+}
+
+#[test]
+fn default_fields() {
+    #[derive(Deserialize_enum, Debug, PartialEq)]
+    enum External {
+        A {
+            x: i32,
+            #[serde(default)]
+            tags: Vec<String>,
+            #[serde(default = "default_port")]
+            port: u16,
+        },
+        #[serde(default)]
+        B { x: i32, y: String },
+    }
+    #[derive(Deserialize_enum, Debug, PartialEq)]
+    #[serde(tag = "type")]
+    enum Internal {
+        A {
+            x: i32,
+            #[serde(default = "default_port")]
+            port: u16,
+        },
+    }
+    #[derive(Deserialize_enum, Debug, PartialEq)]
+    #[serde(tag = "type", content = "content")]
+    enum Adjacent {
+        A {
+            x: i32,
+            #[serde(skip_deserializing, default = "default_port")]
+            port: u16,
+        },
+    }
+    let actual: Vec<External> = json::from_str(r#"[{"A":{"x":1}},{"B":{}}]"#).unwrap();
+    let expected = [
+        External::A {
+            x: 1,
+            tags: vec![],
+            port: 8080,
+        },
+        External::B {
+            x: 0,
+            y: String::new(),
+        },
+    ];
+    assert_eq!(actual, expected);
+    assert!(json::from_str::<External>(r#"{"A":{"port":1}}"#).is_err());
+    let actual: Internal = json::from_str(r#"{"type":"A","x":1}"#).unwrap();
+    assert_eq!(actual, Internal::A { x: 1, port: 8080 });
+    let actual: Adjacent = json::from_str(r#"{"type":"A","content":{"x":1,"port":1}}"#).unwrap();
+    assert_eq!(actual, Adjacent::A { x: 1, port: 8080 });
+}
+
+#[test]
+fn deserialize_with() {
+    #[derive(Deserialize_enum, Debug, PartialEq)]
+    enum External {
+        A {
+            #[serde(deserialize_with = "u64_as_string::deserialize")]
+            id: u64,
+        },
+        B(
+            #[serde(deserialize_with = "u64_as_string::deserialize")] u64,
+            u64,
+        ),
+        #[serde(deserialize_with = "u64_as_string::deserialize")]
+        C(u64),
+    }
+    #[derive(Deserialize_enum, Debug, PartialEq)]
+    #[serde(tag = "type")]
+    enum Internal {
+        A {
+            #[serde(deserialize_with = "u64_as_string::deserialize", default)]
+            id: u64,
+        },
+    }
+    use External::*;
+    let example = r#"[{"A":{"id":"1"}},{"B":["2",3]},{"C":"4"}]"#;
+    let actual: Vec<External> = json::from_str(example).unwrap();
+    assert_eq!(actual, [A { id: 1 }, B(2, 3), C(4)]);
+    assert!(json::from_str::<External>(r#"{"C":4}"#).is_err());
+    let actual: Vec<Internal> = json::from_str(r#"[{"type":"A","id":"5"},{"type":"A"}]"#).unwrap();
+    assert_eq!(actual, [Internal::A { id: 5 }, Internal::A { id: 0 }]);
+}
+
+#[test]
+fn flatten() {
+    use std::collections::BTreeMap;
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Header {
+        id: u32,
+        from: String,
+    }
+    #[derive(Deserialize_enum, Debug, PartialEq)]
+    enum External {
+        A {
+            #[serde(flatten)]
+            header: Header,
+            x: i32,
+        },
+    }
+    #[derive(Deserialize_enum, Debug)]
+    #[serde(tag = "type")]
+    enum Internal {
+        A {
+            x: i32,
+            #[serde(flatten)]
+            extra: BTreeMap<String, json::Value>,
+        },
+    }
+    let actual: External = json::from_str(r#"{"A":{"id":1,"x":2,"from":"me"}}"#).unwrap();
+    let header = Header {
+        id: 1,
+        from: "me".to_string(),
+    };
+    assert_eq!(actual, External::A { header, x: 2 });
+    assert!(json::from_str::<External>(r#"{"A":{"id":1,"x":2}}"#).is_err());
+    assert!(json::from_str::<External>(r#"{"A":{"id":1,"x":2"#).is_err());
+    let actual: Internal = json::from_str(r#"{"type":"A","y":true,"x":1,"z":[]}"#).unwrap();
+    let Internal::A { x, extra } = actual;
+    assert_eq!(x, 1);
+    assert_eq!(extra.keys().collect::<Vec<_>>(), ["y", "z"]);
+    let actual: Internal = json::from_str(r#"{"type":"A","x":1}"#).unwrap();
+    let Internal::A { extra, .. } = actual;
+    assert!(extra.is_empty());
+}
+
+#[test]
+fn unknown_fields() {
+    #[derive(Deserialize_enum, Debug, PartialEq)]
+    enum External {
+        A { x: i32 },
+        B,
+    }
+    #[derive(Deserialize_enum, Debug, PartialEq)]
+    #[serde(tag = "type", content = "content")]
+    enum Adjacent {
+        A { x: i32 },
+        B,
+    }
+    #[derive(Deserialize_enum, Debug, PartialEq)]
+    #[serde(tag = "type")]
+    enum Internal {
+        A { x: i32 },
+        B,
+    }
+    let actual: External = json::from_str(r#"{"A":{"x":1,"y":2},"z":3}"#).unwrap();
+    assert_eq!(actual, External::A { x: 1 });
+    let actual: Adjacent = json::from_str(r#"{"type":"A","z":3,"content":{"x":1,"y":2}}"#).unwrap();
+    assert_eq!(actual, Adjacent::A { x: 1 });
+    let actual: Adjacent = json::from_str(r#"{"type":"B","z":3}"#).unwrap();
+    assert_eq!(actual, Adjacent::B);
+    let actual: Internal = json::from_str(r#"{"type":"A","x":1,"y":2}"#).unwrap();
+    assert_eq!(actual, Internal::A { x: 1 });
+    let actual: Internal = json::from_str(r#"{"type":"B","y":2}"#).unwrap();
+    assert_eq!(actual, Internal::B);
+}
+
+#[test]
+fn deny_unknown_fields() {
+    #[derive(Deserialize_enum, Debug, PartialEq)]
+    #[serde(deny_unknown_fields)]
+    enum External {
+        A { x: i32 },
+        B { y: i32 },
+    }
+    #[derive(Deserialize_enum, Debug, PartialEq)]
+    #[serde(tag = "type", content = "content", deny_unknown_fields)]
+    enum Adjacent {
+        A { x: i32 },
+        B,
+    }
+    #[derive(Deserialize_enum, Debug, PartialEq)]
+    #[serde(tag = "type")]
+    #[serde(deny_unknown_fields)]
+    enum Internal {
+        A { x: i32 },
+        B,
+    }
+    let actual: External = json::from_str(r#"{"A":{"x":1}}"#).unwrap();
+    assert_eq!(actual, External::A { x: 1 });
+    assert!(json::from_str::<External>(r#"{"A":{"x":1,"y":2}}"#).is_err());
+    assert!(json::from_str::<External>(r#"{"A":{"x":1},"z":3}"#).is_err());
+    assert!(json::from_str::<External>(r#"{"A":{"x":1},"B":{"y":2}}"#).is_err());
+    let actual: Adjacent = json::from_str(r#"{"type":"A","content":{"x":1}}"#).unwrap();
+    assert_eq!(actual, Adjacent::A { x: 1 });
+    assert!(json::from_str::<Adjacent>(r#"{"type":"A","content":{"x":1,"y":2}}"#).is_err());
+    assert!(json::from_str::<Adjacent>(r#"{"type":"B","z":3}"#).is_err());
+    let actual: Internal = json::from_str(r#"{"type":"A","x":1}"#).unwrap();
+    assert_eq!(actual, Internal::A { x: 1 });
+    assert!(json::from_str::<Internal>(r#"{"type":"A","x":1,"y":2}"#).is_err());
+    assert!(json::from_str::<Internal>(r#"{"type":"B","y":2}"#).is_err());
+}
+
+#[test]
+fn deny_duplicate_keys() {
+    #[derive(Deserialize_enum, Debug, PartialEq)]
+    #[serde(deny_duplicate_keys)]
+    enum External {
+        A { x: Option<i32> },
+        B { y: i32 },
+    }
+    #[derive(Deserialize_enum, Debug, PartialEq)]
+    #[serde(tag = "type", content = "content", deny_duplicate_keys)]
+    enum Adjacent {
+        A { x: i32 },
+        B,
+    }
+    #[derive(Deserialize_enum, Debug, PartialEq)]
+    #[serde(tag = "type", deny_duplicate_keys)]
+    enum Internal {
+        A { x: i32 },
+        B,
+    }
+    #[derive(Deserialize_enum, Debug, PartialEq)]
+    #[serde(tag = "type")]
+    enum Lenient {
+        A { x: i32 },
+    }
+    let actual: External = json::from_str(r#"{"A":{"x":null}}"#).unwrap();
+    assert_eq!(actual, External::A { x: None });
+    assert!(json::from_str::<External>(r#"{"A":{"x":1},"A":{"x":2}}"#).is_err());
+    assert!(json::from_str::<External>(r#"{"A":{"x":1,"x":2}}"#).is_err());
+    assert!(json::from_str::<External>(r#"{"A":{"x":null,"x":2}}"#).is_err());
+    let actual: Adjacent = json::from_str(r#"{"type":"A","content":{"x":1}}"#).unwrap();
+    assert_eq!(actual, Adjacent::A { x: 1 });
+    assert!(json::from_str::<Adjacent>(r#"{"type":"B","type":"A","content":{"x":1}}"#).is_err());
+    assert!(
+        json::from_str::<Adjacent>(r#"{"type":"A","content":{"x":1},"content":{"x":2}}"#).is_err()
+    );
+    let actual: Internal = json::from_str(r#"{"type":"A","x":1}"#).unwrap();
+    assert_eq!(actual, Internal::A { x: 1 });
+    assert!(json::from_str::<Internal>(r#"{"type":"A","type":"A","x":1}"#).is_err());
+    assert!(json::from_str::<Internal>(r#"{"type":"A","x":1,"x":2}"#).is_err());
+    let actual: Lenient = json::from_str(r#"{"type":"A","x":1,"x":2}"#).unwrap();
+    assert_eq!(actual, Lenient::A { x: 2 });
+}
+
+#[test]
+fn from_and_try_from() {
+    #[derive(Deserialize_enum, Debug, PartialEq)]
+    enum Wire {
+        Count { count: i32 },
+        Name(String),
+    }
+    #[derive(Deserialize_enum, Debug, PartialEq)]
+    #[serde(from = "Wire")]
+    enum Domain {
+        Count(i32),
+        Name(String),
+    }
+    impl From<Wire> for Domain {
+        fn from(wire: Wire) -> Self {
+            match wire {
+                Wire::Count { count } => Domain::Count(count),
+                Wire::Name(name) => Domain::Name(name),
+            }
+        }
+    }
+    #[derive(Deserialize_enum, Debug, PartialEq)]
+    #[serde(try_from = "Wire")]
+    enum Positive {
+        Count(u32),
+    }
+    impl std::convert::TryFrom<Wire> for Positive {
+        type Error = ();
+        fn try_from(wire: Wire) -> Result<Self, ()> {
+            match wire {
+                Wire::Count { count } if count > 0 => Ok(Positive::Count(count as u32)),
+                _ => Err(()),
+            }
+        }
+    }
+    #[derive(Deserialize_enum, Debug, PartialEq)]
+    #[serde(from = "Vec<u32>")]
+    enum Digits {
+        Many(Vec<u32>),
+    }
+    impl From<Vec<u32>> for Digits {
+        fn from(digits: Vec<u32>) -> Self {
+            Digits::Many(digits)
+        }
+    }
+    #[derive(Deserialize_enum, Debug, PartialEq)]
+    #[serde(try_from = "String")]
+    enum Level {
+        Low,
+        High,
+    }
+    impl std::convert::TryFrom<String> for Level {
+        type Error = String;
+        fn try_from(level: String) -> Result<Self, String> {
+            match level.as_str() {
+                "low" => Ok(Level::Low),
+                "high" => Ok(Level::High),
+                _ => Err(level),
+            }
+        }
+    }
+    let actual: Domain = json::from_str(r#"{"Count":{"count":3}}"#).unwrap();
+    assert_eq!(actual, Domain::Count(3));
+    let actual: Domain = json::from_str(r#"{"Name":"a"}"#).unwrap();
+    assert_eq!(actual, Domain::Name("a".to_owned()));
+    let actual: Positive = json::from_str(r#"{"Count":{"count":3}}"#).unwrap();
+    assert_eq!(actual, Positive::Count(3));
+    assert!(json::from_str::<Positive>(r#"{"Count":{"count":-3}}"#).is_err());
+    assert!(json::from_str::<Positive>(r#"{"Name":"a"}"#).is_err());
+    let actual: Digits = json::from_str("[1,2]").unwrap();
+    assert_eq!(actual, Digits::Many(vec![1, 2]));
+    assert!(json::from_str::<Digits>("[1,2").is_err());
+    let actual: Vec<Level> = json::from_str(r#"["low","high"]"#).unwrap();
+    assert_eq!(actual, [Level::Low, Level::High]);
+    assert!(json::from_str::<Level>(r#""medium""#).is_err());
+}
+
+#[test]
+fn remote() {
+    mod other {
+        #[derive(Debug, PartialEq)]
+        pub enum Shape {
+            Circle { radius: u32 },
+            Square(u32),
+        }
+    }
+    #[derive(Deserialize_enum)]
+    #[serde(remote = "other::Shape")]
+    #[allow(dead_code)]
+    enum ShapeDef {
+        Circle { radius: u32 },
+        Square(u32),
+    }
+    #[derive(Deserialize_enum)]
+    #[serde(remote = "std::cmp::Ordering")]
+    #[allow(dead_code)]
+    enum OrderingDef {
+        Less,
+        Equal,
+        Greater,
+    }
+    #[derive(Deserialize_enum)]
+    #[serde(tag = "type", remote = "std::net::Shutdown")]
+    #[allow(dead_code)]
+    enum ShutdownDef {
+        Read,
+        Write,
+        Both,
+    }
+    #[derive(Deserialize_enum, Debug, PartialEq)]
+    enum Comparison {
+        Shapes {
+            #[serde(with = "ShapeDef")]
+            shape: other::Shape,
+            #[serde(with = "OrderingDef")]
+            ordering: std::cmp::Ordering,
+        },
+        Shutdown(#[serde(with = "ShutdownDef")] std::net::Shutdown),
+    }
+    let actual: Comparison =
+        json::from_str(r#"{"Shapes":{"shape":{"Square":3},"ordering":"Equal"}}"#).unwrap();
+    let expected = Comparison::Shapes {
+        shape: other::Shape::Square(3),
+        ordering: std::cmp::Ordering::Equal,
+    };
+    assert_eq!(actual, expected);
+    let actual: Comparison = json::from_str(r#"{"Shutdown":{"type":"Both"}}"#).unwrap();
+    assert_eq!(actual, Comparison::Shutdown(std::net::Shutdown::Both));
+}
+
+#[test]
+fn bound() {
+    struct NotDeserialize;
+    #[derive(Deserialize_enum, Debug, PartialEq)]
+    #[serde(bound(deserialize = ""))]
+    enum Level<T> {
+        Low,
+        High,
+        #[serde(skip)]
+        #[allow(dead_code)]
+        Marker(std::marker::PhantomData<T>),
+    }
+    let actual: Level<NotDeserialize> = json::from_str(r#""High""#).unwrap();
+    assert!(matches!(actual, Level::High));
+}
+
+#[test]
+fn inferred_bounds() {
+    struct NotDeserialize;
+    #[derive(Deserialize_enum)]
+    enum Level<T> {
+        Low,
+        High,
+        #[serde(skip_deserializing)]
+        #[allow(dead_code)]
+        Marker(std::marker::PhantomData<T>),
+    }
+    let actual: Level<NotDeserialize> = json::from_str(r#""Low""#).unwrap();
+    assert!(matches!(actual, Level::Low));
+}
+
+#[test]
+fn generic_tagged() {
+    struct NotDeserialize;
+    #[derive(Deserialize_enum, Debug, PartialEq)]
+    #[serde(tag = "type")]
+    enum Internal<T> {
+        Pending,
+        Err {
+            msg: String,
+        },
+        #[serde(skip_deserializing)]
+        #[allow(dead_code)]
+        Marker {
+            marker: std::marker::PhantomData<T>,
+        },
+    }
+    #[derive(Deserialize_enum, Debug, PartialEq)]
+    #[serde(tag = "type", content = "content")]
+    enum Adjacent<T> {
+        Pending,
+        Err(String, u32),
+        #[serde(skip_deserializing)]
+        #[allow(dead_code)]
+        Marker(std::marker::PhantomData<T>),
+    }
+    let actual: Internal<NotDeserialize> =
+        json::from_str(r#"{"type":"Err","msg":"timeout"}"#).unwrap();
+    assert!(matches!(actual, Internal::Err { msg } if msg == "timeout"));
+    let actual: Internal<NotDeserialize> = json::from_str(r#"{"type":"Pending"}"#).unwrap();
+    assert!(matches!(actual, Internal::Pending));
+    let actual: Adjacent<NotDeserialize> =
+        json::from_str(r#"{"type":"Err","content":["timeout",504]}"#).unwrap();
+    assert!(matches!(actual, Adjacent::Err(msg, 504) if msg == "timeout"));
+}
+
+#[test]
+fn generic_payloads() {
+    #[derive(Deserialize_enum, Debug, PartialEq)]
+    enum External<T> {
+        One(T),
+        Pair(T, String),
+        Many { items: Vec<T> },
+        Empty,
+    }
+    #[derive(Deserialize_enum, Debug, PartialEq)]
+    #[serde(tag = "type")]
+    enum Internal<T> {
+        Ok { data: T },
+        Err { msg: String },
+    }
+    #[derive(Deserialize_enum, Debug, PartialEq)]
+    #[serde(tag = "t", content = "c")]
+    enum Adjacent<T> {
+        One(T),
+        Pair(T, String),
+        Many { items: Vec<T> },
+    }
+    let example = r#"[{"One":1},{"Pair":[2,"b"]},{"Many":{"items":[3,4]}},"Empty"]"#;
+    let actual: Vec<External<u8>> = json::from_str(example).unwrap();
+    let expected = [
+        External::One(1),
+        External::Pair(2, "b".to_string()),
+        External::Many { items: vec![3, 4] },
+        External::Empty,
+    ];
+    assert_eq!(actual, expected);
+    let example = r#"[{"type":"Ok","data":[1,2]},{"type":"Err","msg":"no"}]"#;
+    let actual: Vec<Internal<Vec<u8>>> = json::from_str(example).unwrap();
+    let expected = [
+        Internal::Ok { data: vec![1, 2] },
+        Internal::Err {
+            msg: "no".to_string(),
+        },
+    ];
+    assert_eq!(actual, expected);
+    let example =
+        r#"[{"t":"One","c":true},{"t":"Pair","c":[false,"b"]},{"t":"Many","c":{"items":[]}}]"#;
+    let actual: Vec<Adjacent<bool>> = json::from_str(example).unwrap();
+    let expected = [
+        Adjacent::One(true),
+        Adjacent::Pair(false, "b".to_string()),
+        Adjacent::Many { items: vec![] },
+    ];
+    assert_eq!(actual, expected);
+}
+
+#[test]
+fn const_generics() {
+    #[derive(Deserialize_enum, Debug, PartialEq)]
+    enum Frame<const N: usize> {
+        Data([u8; N]),
+        Header { id: u16, bytes: [u8; N] },
+        Pair([u8; N], [i8; 2]),
+        Empty,
+    }
+    #[derive(Deserialize_enum, Debug, PartialEq)]
+    #[serde(tag = "type")]
+    enum Points<T> {
+        Line { ends: [T; 2] },
+    }
+    let example = r#"[{"Data":[1,2,3]},{"Header":{"id":7,"bytes":[4,5,6]}},{"Pair":[[0,0,0],[-1,1]]},"Empty"]"#;
+    let actual: Vec<Frame<3>> = json::from_str(example).unwrap();
+    let expected = [
+        Frame::Data([1, 2, 3]),
+        Frame::Header {
+            id: 7,
+            bytes: [4, 5, 6],
+        },
+        Frame::Pair([0; 3], [-1, 1]),
+        Frame::Empty,
+    ];
+    assert_eq!(actual, expected);
+    assert!(json::from_str::<Frame<3>>(r#"{"Data":[1,2]}"#).is_err());
+    assert!(json::from_str::<Frame<3>>(r#"{"Data":[1,2,3,4]}"#).is_err());
+    let actual: Points<f64> = json::from_str(r#"{"type":"Line","ends":[1.5,2.0]}"#).unwrap();
+    assert_eq!(actual, Points::Line { ends: [1.5, 2.0] });
+}
+
+#[test]
+fn borrowed_fields() {
+    use std::borrow::Cow;
+
+    #[derive(Deserialize_enum, Debug, PartialEq)]
+    enum Log<'a> {
+        Msg { text: Cow<'a, str> },
+        Tag(Cow<'a, str>),
+        Bytes(Cow<'a, [u8]>, u8),
+        Empty,
+    }
+    #[derive(Deserialize_enum, Debug, PartialEq)]
+    #[serde(tag = "kind")]
+    enum Internal<'a> {
+        Msg { text: Cow<'a, str> },
+    }
+    let example = r#"[{"Msg":{"text":"hello"}},{"Tag":"tag"},{"Bytes":[[1,2],3]},"Empty"]"#;
+    let actual: Vec<Log> = json::from_str(example).unwrap();
+    assert!(actual.iter().all(|log| match log {
+        Log::Msg { text } | Log::Tag(text) => matches!(text, Cow::Owned(_)),
+        Log::Bytes(bytes, _) => matches!(bytes, Cow::Owned(_)),
+        Log::Empty => true,
+    }));
+    let expected = [
+        Log::Msg {
+            text: "hello".into(),
+        },
+        Log::Tag("tag".into()),
+        Log::Bytes(vec![1, 2].into(), 3),
+        Log::Empty,
+    ];
+    assert_eq!(actual, expected);
+    let actual: Internal = json::from_str(r#"{"kind":"Msg","text":"hi"}"#).unwrap();
+    assert_eq!(actual, Internal::Msg { text: "hi".into() });
+}
+
+#[test]
+fn user_defined_cow() {
+    mod mine {
+        use miniserde::Deserialize;
+
+        #[derive(Deserialize, Debug, PartialEq)]
+        pub struct Cow<T> {
+            pub inner: T,
+        }
+    }
+    use mine::Cow;
+
+    #[derive(Deserialize_enum, Debug, PartialEq)]
+    enum Wrapped {
+        Bare(Cow<u8>),
+        Qualified { cow: mine::Cow<String> },
+    }
+    let example = r#"[{"Bare":{"inner":1}},{"Qualified":{"cow":{"inner":"a"}}}]"#;
+    let actual: Vec<Wrapped> = json::from_str(example).unwrap();
+    let expected = [
+        Wrapped::Bare(Cow { inner: 1 }),
+        Wrapped::Qualified {
+            cow: Cow {
+                inner: "a".to_owned(),
+            },
+        },
+    ];
+    assert_eq!(actual, expected);
+}
+
+#[test]
+fn cfg_attributes() {
+    #[allow(dead_code)]
+    struct NotDeserialize;
+    #[derive(Deserialize_enum, Debug, PartialEq)]
+    #[serde(tag = "type")]
+    enum Connection {
+        Tcp {
+            #[cfg_attr(all(), serde(rename = "address"))]
+            addr: String,
+            #[cfg(any())]
+            proxy: NotDeserialize,
+            #[cfg_attr(any(), serde(skip))]
+            port: u16,
+        },
+        #[cfg(any())]
+        Tls { config: NotDeserialize },
+        #[cfg_attr(all(), serde(rename = "local"))]
+        Unix,
+    }
+    let example = r#"[{"type":"Tcp","address":"::1","port":80},{"type":"local"}]"#;
+    let actual: Vec<Connection> = json::from_str(example).unwrap();
+    let expected = [
+        Connection::Tcp {
+            addr: "::1".to_string(),
+            port: 80,
+        },
+        Connection::Unix,
+    ];
+    assert_eq!(actual, expected);
+    assert!(json::from_str::<Connection>(r#"{"type":"Tls"}"#).is_err());
+}
+
+#[test]
+#[allow(non_camel_case_types)]
+fn raw_identifiers() {
+    #[derive(Deserialize_enum, Debug, PartialEq)]
+    enum r#enum {
+        Node {
+            r#type: u8,
+            r#match: bool,
+            r#ref: String,
+        },
+        r#struct(u8),
+        r#fn,
+    }
+    #[derive(Deserialize_enum, Debug, PartialEq)]
+    #[serde(tag = "kind")]
+    enum Internal {
+        r#struct { r#type: u8, r#match: bool },
+    }
+    let example = r#"[{"Node":{"type":1,"match":true,"ref":"r"}},{"struct":2},"fn"]"#;
+    let actual: Vec<r#enum> = json::from_str(example).unwrap();
+    let expected = [
+        r#enum::Node {
+            r#type: 1,
+            r#match: true,
+            r#ref: "r".to_string(),
+        },
+        r#enum::r#struct(2),
+        r#enum::r#fn,
+    ];
+    assert_eq!(actual, expected);
+    let actual: Internal = json::from_str(r#"{"kind":"struct","type":1,"match":false}"#).unwrap();
+    let expected = Internal::r#struct {
+        r#type: 1,
+        r#match: false,
+    };
+    assert_eq!(actual, expected);
+}
+
+#[test]
+fn adversarial_names() {
+    use common::adversarial_names::{Adjacent, External, Internal};
+    use std::borrow::Cow;
+
+    let example = r#"[{"__Visitor":{"state":1,"data":"d","__out":2,"__marker":"m","__state":"s","__seen":true}},{"__State":[3,4]},"__Map","__AsStruct"]"#;
+    let actual: Vec<External<u8>> = json::from_str(example).unwrap();
+    let expected = [
+        External::__Visitor {
+            state: 1,
+            data: "d".to_string(),
+            __out: 2,
+            __marker: Cow::Borrowed("m"),
+            __state: Cow::Borrowed("s"),
+            __seen: true,
+        },
+        External::__State(3, 4),
+        External::__Map,
+        External::__AsStruct,
+    ];
+    assert_eq!(actual, expected);
+    let actual: Internal =
+        json::from_str(r#"{"type":"__State","__out":1,"__tag":2,"__seen":3}"#).unwrap();
+    let expected = Internal::__State {
+        __out: 1,
+        __tag: 2,
+        __seen: 3,
+    };
+    assert_eq!(actual, expected);
+    let example = r#"[{"t":"__Visitor","c":{"state":1,"__out":2}},{"t":"__Seq","c":[3,4]}]"#;
+    let actual: Vec<Adjacent> = json::from_str(example).unwrap();
+    let expected = [
+        Adjacent::__Visitor { state: 1, __out: 2 },
+        Adjacent::__Seq(3, 4),
+    ];
+    assert_eq!(actual, expected);
+}
+
+#[test]
+fn local_names() {
+    use common::local_names::{External, Internal};
+
+    let actual: Vec<External> = json::from_str(r#"[{"Key":{"k":1}},{"Tuple":[2,3]}]"#).unwrap();
+    assert_eq!(actual, [External::Key { k: 1 }, External::Tuple(2, 3)]);
+    let actual: Internal = json::from_str(r#"{"type":"Hex","hex":10}"#).unwrap();
+    assert_eq!(actual, Internal::Hex { hex: 10 });
+}
+
+#[test]
+fn strict_lints() {
+    use common::strict::{Adjacent, Converted, External, Header, Internal, OrderingDef, Wire};
+
+    let example = r#"[{"Named":{"value":1,"array":[2],"text":"a","id":3}},{"Tuple":[4,[5]]},{"Newtype":6},"Unit"]"#;
+    let actual: Vec<External<u8, 1>> = json::from_str(example).unwrap();
+    let expected = [
+        External::Named {
+            value: 1,
+            array: [2],
+            text: "a".into(),
+            list: vec![],
+            header: Header { id: 3 },
+            skipped: false,
+        },
+        External::Tuple(4, [5]),
+        External::Newtype(6),
+        External::Unit,
+    ];
+    assert_eq!(actual, expected);
+    let actual: Internal = json::from_str(r#"{"type":"Named","x":1,"id":2}"#).unwrap();
+    let expected = Internal::Named {
+        x: 1,
+        header: Header { id: 2 },
+    };
+    assert_eq!(actual, expected);
+    let example = r#"[{"t":"Named","c":{"x":1}},{"t":"Tuple","c":[2,3]},{"t":"Unit"}]"#;
+    let actual: Vec<Adjacent> = json::from_str(example).unwrap();
+    let expected = [
+        Adjacent::Named { x: 1 },
+        Adjacent::Tuple(2, 3),
+        Adjacent::Unit,
+    ];
+    assert_eq!(actual, expected);
+    let actual: Wire = json::from_str(r#"{"Named":{"camelCase":1}}"#).unwrap();
+    assert_eq!(actual, Wire::Named { camelCase: 1 });
+    let actual: Converted = json::from_str(r#"{"type":"Unit"}"#).unwrap();
+    assert_eq!(actual, Converted::Unit);
+    let mut out = None;
+    miniserde::de::Visitor::string(OrderingDef::deserialize(&mut out), "Less").unwrap();
+    assert_eq!(out, Some(std::cmp::Ordering::Less));
+}
+
+#[test]
+fn field_attributes() {
+    use common::field_attributes::{Adjacent, External, Internal};
+
+    let fields = r#""y":1,"skipped_serializing":2,"skipped_deserializing":3,"id":"4""#;
+    let actual: External = json::from_str(&format!(r#"{{"C":{{{}}}}}"#, fields)).unwrap();
+    assert_eq!(actual, External::example());
+    let actual: Internal = json::from_str(&format!(r#"{{"type":"C",{}}}"#, fields)).unwrap();
+    assert_eq!(actual, Internal::example());
+    let example = format!(r#"{{"type":"C","content":{{{}}}}}"#, fields);
+    let actual: Adjacent = json::from_str(&example).unwrap();
+    assert_eq!(actual, Adjacent::example());
+}
+
+#[test]
+fn variant_attributes() {
+    #[derive(Deserialize_enum, Debug, PartialEq)]
+    enum External {
+        /// A point.
+        #[cfg(test)]
+        #[cfg_attr(all(), serde(rename = "point"))]
+        #[allow(clippy::all)]
+        #[deprecated]
+        Point { x: i32 },
+        /// A pair.
+        #[cfg(test)]
+        #[serde(rename = "pair")]
+        #[deprecated]
+        Pair(i32, i32),
+    }
+    let example = r#"[{"point":{"x":1}},{"pair":[2,3]}]"#;
+    let actual: Vec<External> = json::from_str(example).unwrap();
+    #[allow(deprecated)]
+    let expected = [External::Point { x: 1 }, External::Pair(2, 3)];
+    assert_eq!(actual, expected);
+}
+
+#[test]
+fn missing_fields() {
+    type Maybe<T> = Option<T>;
+
+    #[derive(Deserialize_enum)]
+    enum External {
+        A {
+            x: i32,
+            label: Option<String>,
+            note: std::option::Option<String>,
+            alias: Maybe<String>,
+        },
+        B {
+            x: i32,
+            v: json::Value,
+        },
+    }
+    let actual: External = json::from_str(r#"{"A":{"x":1}}"#).unwrap();
+    assert!(matches!(
+        actual,
+        External::A {
+            x: 1,
+            label: None,
+            note: None,
+            alias: None
+        }
+    ));
+    assert!(json::from_str::<External>(r#"{"A":{"label":"a"}}"#).is_err());
+    let actual: External = json::from_str(r#"{"B":{"x":1}}"#).unwrap();
+    assert!(matches!(
+        actual,
+        External::B {
+            x: 1,
+            v: json::Value::Null
+        }
+    ));
+}
+
+#[test]
+fn crate_path() {
+    use common::crate_path::Event;
+
+    let actual: Vec<Event> = json::from_str(r#"[{"Start":{"at":3}},{"Stop":4},"Reset"]"#).unwrap();
+    assert_eq!(
+        actual,
+        [Event::Start { at: 3 }, Event::Stop(4), Event::Reset]
+    );
+}
+
+#[test]
+fn shadowed_prelude() {
+    use common::shadowed_prelude::{Adjacent, External, Internal};
+
+    let actual: Vec<External> =
+        json::from_str(r#"["Unit",{"Named":{"at":1}},{"Newtype":2},{"Tuple":[3,4]}]"#).unwrap();
+    let expected = [
+        External::Unit,
+        External::Named { at: 1, count: 0 },
+        External::Newtype(2),
+        External::Tuple(3, 4),
+    ];
+    assert_eq!(actual, expected);
+    let actual: Vec<Internal> =
+        json::from_str(r#"[{"type":"Unit"},{"type":"Named","at":1}]"#).unwrap();
+    assert_eq!(actual, [Internal::Unit, Internal::Named { at: 1 }]);
+    let actual: Vec<Adjacent> =
+        json::from_str(r#"[{"type":"Unit"},{"type":"Named","content":{"at":1}}]"#).unwrap();
+    assert_eq!(actual, [Adjacent::Unit, Adjacent::Named { at: 1 }]);
+}
+
+#[test]
+fn public_api_only() {
+    use common::public_api_only::{Adjacent, External, Internal};
+
+    let example = r#"[{"A":{"x":1}},{"B":[2,"two"]},{"C":true},"D"]"#;
+    let actual: Vec<External> = json::from_str(example).unwrap();
+    let expected = [
+        External::A { x: 1, label: None },
+        External::B(2, "two".to_string()),
+        External::C(true),
+        External::D,
+    ];
+    assert_eq!(actual, expected);
+    assert!(json::from_str::<External>(r#"{"A":{"label":"a"}}"#).is_err());
+    let actual: Vec<Internal> = json::from_str(r#"[{"type":"A","x":1},{"type":"B"}]"#).unwrap();
+    assert_eq!(actual, [Internal::A { x: 1 }, Internal::B]);
+    let example = r#"[{"t":"A","c":{"x":1}},{"t":"B","c":[2,3]},{"t":"C"}]"#;
+    let actual: Vec<Adjacent> = json::from_str(example).unwrap();
+    assert_eq!(
+        actual,
+        [Adjacent::A { x: 1 }, Adjacent::B(2, 3), Adjacent::C]
+    );
+}
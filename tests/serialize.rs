@@ -1,10 +1,13 @@
+pub mod common;
+
+use common::u64_as_string;
 use miniserde::{json, Serialize};
 use miniserde_enum::Serialize_enum;
 
 #[test]
 fn test_internal() {
-    #[serde(tag = "type")]
     #[derive(Serialize_enum)]
+    #[serde(tag = "type")]
     enum Internal {
         A,
         #[serde(rename = "renamedB")]
@@ -41,8 +44,8 @@ fn test_external() {
 
 #[test]
 fn test_untagged() {
-    #[serde(untagged)]
     #[derive(Serialize_enum)]
+    #[serde(untagged)]
     enum Untagged {
         A(i32),
         #[serde(rename = "renamedB")]
@@ -86,3 +89,682 @@ fn generic_unnamed() {
     let expected = r#"[{"A":"abc"},"B"]"#;
     assert_eq!(actual, expected);
 }
+
+#[test]
+fn skipped_variants() {
+    #[derive(Serialize_enum)]
+    #[serde(tag = "type")]
+    #[allow(dead_code)]
+    enum Skipped {
+        A,
+        #[serde(skip_serializing = "redacted")]
+        B {
+            secret: String,
+        },
+        #[serde(skip = "internal")]
+        C(i32, i32),
+        #[serde(skip_deserializing)]
+        D,
+    }
+    use Skipped::*;
+    let example = [
+        A,
+        B {
+            secret: "hunter2".to_string(),
+        },
+        C(1, 2),
+        D,
+    ];
+    let actual = json::to_string(&example[..]);
+    let expected = r#"[{"type":"A"},{"type":"redacted"},{"type":"internal"},{"type":"D"}]"#;
+    assert_eq!(actual, expected);
+}
+
+#[test]
+#[should_panic(expected = "the enum variant Skipped::B cannot be serialized")]
+fn skipped_variant_fails() {
+    #[derive(Serialize_enum)]
+    #[allow(dead_code)]
+    enum Skipped {
+        A,
+        #[serde(skip)]
+        B(i32),
+    }
+    json::to_string(&Skipped::B(0));
+}
+
+#[test]
+fn skipped_fields() {
+    #[derive(Serialize_enum)]
+    #[allow(dead_code)]
+    enum External {
+        A {
+            x: i32,
+            #[serde(skip)]
+            hash: u64,
+            #[serde(skip_serializing)]
+            cached: Option<String>,
+        },
+        B {
+            #[serde(skip_serializing)]
+            y: i32,
+        },
+    }
+    #[derive(Serialize_enum)]
+    #[serde(tag = "type")]
+    #[allow(dead_code)]
+    enum Internal {
+        A {
+            #[serde(skip_serializing)]
+            hash: u64,
+            x: i32,
+        },
+    }
+    let example = [
+        External::A {
+            x: 1,
+            hash: 2,
+            cached: None,
+        },
+        External::B { y: 3 },
+    ];
+    let actual = json::to_string(&example[..]);
+    let expected = r#"[{"A":{"x":1}},{"B":{}}]"#;
+    assert_eq!(actual, expected);
+    let actual = json::to_string(&Internal::A { hash: 2, x: 1 });
+    assert_eq!(actual, r#"{"type":"A","x":1}"#);
+}
+
+#[test]
+fn skip_serializing_if() {
+    #[derive(Serialize_enum)]
+    enum External {
+        A {
+            #[serde(skip_serializing_if = "Option::is_none")]
+            x: Option<i32>,
+            #[serde(skip_serializing_if = "Vec::is_empty")]
+            ys: Vec<i32>,
+        },
+    }
+    #[derive(Serialize_enum)]
+    #[serde(tag = "type")]
+    enum Internal {
+        A {
+            #[serde(skip_serializing_if = "Option::is_none")]
+            x: Option<i32>,
+            z: i32,
+        },
+    }
+    let example = [
+        External::A {
+            x: None,
+            ys: vec![],
+        },
+        External::A {
+            x: Some(1),
+            ys: vec![2],
+        },
+    ];
+    let actual = json::to_string(&example[..]);
+    let expected = r#"[{"A":{}},{"A":{"x":1,"ys":[2]}}]"#;
+    assert_eq!(actual, expected);
+    let example = [
+        Internal::A { x: None, z: 1 },
+        Internal::A { x: Some(2), z: 3 },
+    ];
+    let actual = json::to_string(&example[..]);
+    let expected = r#"[{"type":"A","z":1},{"type":"A","x":2,"z":3}]"#;
+    assert_eq!(actual, expected);
+}
+
+#[allow(clippy::ptr_arg)]
+fn hex(bytes: &Vec<u8>) -> miniserde::ser::Fragment<'_> {
+    let hex = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    miniserde::ser::Fragment::Str(std::borrow::Cow::Owned(hex))
+}
+
+#[test]
+fn serialize_with() {
+    #[derive(Serialize_enum)]
+    enum External {
+        A {
+            #[serde(serialize_with = "u64_as_string::serialize")]
+            id: u64,
+            #[serde(serialize_with = "hex", skip_serializing_if = "Vec::is_empty")]
+            bytes: Vec<u8>,
+        },
+        B(#[serde(serialize_with = "hex")] Vec<u8>, u64),
+        #[serde(serialize_with = "u64_as_string::serialize")]
+        C(u64),
+    }
+    #[derive(Serialize_enum)]
+    #[serde(tag = "type")]
+    enum Internal {
+        A {
+            #[serde(serialize_with = "u64_as_string::serialize")]
+            id: u64,
+        },
+    }
+    let example = [
+        External::A {
+            id: 1,
+            bytes: vec![],
+        },
+        External::A {
+            id: 2,
+            bytes: vec![0xab, 0x01],
+        },
+        External::B(vec![0xff], 3),
+        External::C(4),
+    ];
+    let actual = json::to_string(&example[..]);
+    let expected = r#"[{"A":{"id":"1"}},{"A":{"id":"2","bytes":"ab01"}},{"B":["ff",3]},{"C":"4"}]"#;
+    assert_eq!(actual, expected);
+    let actual = json::to_string(&Internal::A { id: 5 });
+    assert_eq!(actual, r#"{"type":"A","id":"5"}"#);
+}
+
+#[test]
+fn flatten() {
+    use std::collections::BTreeMap;
+
+    #[derive(Serialize)]
+    struct Header {
+        id: u32,
+        from: String,
+    }
+    #[derive(Serialize_enum)]
+    enum External {
+        A {
+            #[serde(flatten)]
+            header: Header,
+            x: i32,
+        },
+    }
+    #[derive(Serialize_enum)]
+    #[serde(tag = "type")]
+    enum Internal {
+        A {
+            x: i32,
+            #[serde(flatten)]
+            extra: BTreeMap<String, json::Value>,
+        },
+    }
+    let header = Header {
+        id: 1,
+        from: "me".to_string(),
+    };
+    let actual = json::to_string(&External::A { header, x: 2 });
+    assert_eq!(actual, r#"{"A":{"id":1,"from":"me","x":2}}"#);
+    let mut extra = BTreeMap::new();
+    extra.insert("y".to_string(), json::Value::Bool(true));
+    extra.insert("z".to_string(), json::Value::Null);
+    let example = [
+        Internal::A {
+            x: 1,
+            extra: BTreeMap::new(),
+        },
+        Internal::A { x: 2, extra },
+    ];
+    let actual = json::to_string(&example[..]);
+    let expected = r#"[{"type":"A","x":1},{"type":"A","x":2,"y":true,"z":null}]"#;
+    assert_eq!(actual, expected);
+}
+
+#[test]
+fn into() {
+    #[derive(Serialize_enum)]
+    #[serde(tag = "type")]
+    enum Wire {
+        Span { start: u32, end: u32 },
+    }
+    #[derive(Serialize_enum, Clone)]
+    #[serde(into = "Wire")]
+    enum Domain {
+        Range(std::ops::Range<u32>),
+    }
+    impl From<Domain> for Wire {
+        fn from(domain: Domain) -> Self {
+            match domain {
+                Domain::Range(range) => Wire::Span {
+                    start: range.start,
+                    end: range.end,
+                },
+            }
+        }
+    }
+    #[derive(Serialize_enum, Clone)]
+    #[serde(into = "Vec<u32>")]
+    enum Digits {
+        Two(u32, u32),
+    }
+    impl From<Digits> for Vec<u32> {
+        fn from(digits: Digits) -> Self {
+            match digits {
+                Digits::Two(a, b) => vec![a, b],
+            }
+        }
+    }
+    #[derive(Serialize_enum, Clone)]
+    #[serde(into = "String")]
+    enum Level {
+        Low,
+    }
+    impl From<Level> for String {
+        fn from(_: Level) -> Self {
+            "low".to_owned()
+        }
+    }
+    let actual = json::to_string(&Domain::Range(1..4));
+    assert_eq!(actual, r#"{"type":"Span","start":1,"end":4}"#);
+    let actual = json::to_string(&Digits::Two(1, 2));
+    assert_eq!(actual, "[1,2]");
+    let actual = json::to_string(&Level::Low);
+    assert_eq!(actual, r#""low""#);
+}
+
+#[test]
+fn remote() {
+    mod other {
+        pub enum Shape {
+            Circle { radius: u32 },
+            Square(u32),
+        }
+    }
+    #[derive(Serialize_enum)]
+    #[serde(remote = "other::Shape")]
+    #[allow(dead_code)]
+    enum ShapeDef {
+        Circle { radius: u32 },
+        Square(u32),
+    }
+    #[derive(Serialize_enum)]
+    #[serde(remote = "std::cmp::Ordering")]
+    #[allow(dead_code)]
+    enum OrderingDef {
+        Less,
+        Equal,
+        Greater,
+    }
+    #[derive(Serialize_enum)]
+    enum Comparison {
+        Shapes {
+            #[serde(with = "ShapeDef")]
+            shape: other::Shape,
+            #[serde(with = "OrderingDef")]
+            ordering: std::cmp::Ordering,
+        },
+        Ordering(#[serde(with = "OrderingDef")] std::cmp::Ordering),
+    }
+    let example = Comparison::Shapes {
+        shape: other::Shape::Circle { radius: 2 },
+        ordering: std::cmp::Ordering::Less,
+    };
+    let actual = json::to_string(&example);
+    assert_eq!(
+        actual,
+        r#"{"Shapes":{"shape":{"Circle":{"radius":2}},"ordering":"Less"}}"#
+    );
+    let actual = json::to_string(&Comparison::Ordering(std::cmp::Ordering::Greater));
+    assert_eq!(actual, r#"{"Ordering":"Greater"}"#);
+    let fragment = ShapeDef::serialize(&other::Shape::Square(3));
+    assert!(matches!(fragment, miniserde::ser::Fragment::Map(_)));
+}
+
+#[test]
+fn bound() {
+    // Serialized through Display, so T itself need not implement Serialize.
+    struct Shown<T>(T);
+    impl<T: std::fmt::Display> Serialize for Shown<T> {
+        fn begin(&self) -> miniserde::ser::Fragment<'_> {
+            miniserde::ser::Fragment::Str(self.0.to_string().into())
+        }
+    }
+    struct Id(u32);
+    impl std::fmt::Display for Id {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "#{}", self.0)
+        }
+    }
+    #[derive(Serialize_enum)]
+    #[serde(bound = "T: std::fmt::Display")]
+    enum Message<T> {
+        Payload { id: Shown<T> },
+    }
+    #[derive(Serialize_enum)]
+    enum Mixed<T> {
+        #[serde(bound(serialize = "T: std::fmt::Display"))]
+        Payload(Shown<T>),
+        #[serde(bound = "")]
+        Empty,
+    }
+    let example = Message::Payload { id: Shown(Id(1)) };
+    let actual = json::to_string(&example);
+    assert_eq!(actual, r##"{"Payload":{"id":"#1"}}"##);
+    let example = [Mixed::Payload(Shown(Id(2))), Mixed::Empty];
+    let actual = json::to_string(&example[..]);
+    assert_eq!(actual, r##"[{"Payload":"#2"},"Empty"]"##);
+}
+
+#[test]
+fn inferred_bounds() {
+    trait Codec {
+        type Wire;
+    }
+    struct Text;
+    impl Codec for Text {
+        type Wire = String;
+    }
+    struct NotSerialize;
+    #[derive(Serialize_enum)]
+    enum Message<T: Codec> {
+        Payload(T::Wire),
+        Pair(T::Wire, u32),
+    }
+    #[derive(Serialize_enum)]
+    #[serde(tag = "type")]
+    enum Tagged<T: Codec, U, V> {
+        Payload {
+            wire: T::Wire,
+            #[serde(skip)]
+            marker: std::marker::PhantomData<U>,
+            #[serde(skip)]
+            #[allow(dead_code)]
+            skipped: Option<V>,
+        },
+        Empty,
+    }
+    let example = [
+        Message::<Text>::Payload("abc".to_owned()),
+        Message::Pair("def".to_owned(), 1),
+    ];
+    let actual = json::to_string(&example[..]);
+    assert_eq!(actual, r#"[{"Payload":"abc"},{"Pair":["def",1]}]"#);
+    let example = [
+        Tagged::<Text, NotSerialize, NotSerialize>::Payload {
+            wire: "abc".to_owned(),
+            marker: std::marker::PhantomData,
+            skipped: None,
+        },
+        Tagged::Empty,
+    ];
+    let actual = json::to_string(&example[..]);
+    assert_eq!(
+        actual,
+        r#"[{"type":"Payload","wire":"abc"},{"type":"Empty"}]"#
+    );
+}
+
+#[test]
+fn const_generics() {
+    #[derive(Serialize_enum)]
+    enum Frame<const N: usize> {
+        Data([u8; N]),
+        Header { id: u16, bytes: [u8; N] },
+        Pair([u8; N], [i8; 2]),
+        Empty,
+    }
+    #[derive(Serialize_enum)]
+    #[serde(tag = "type")]
+    enum Points<T> {
+        Line { ends: [T; 2] },
+    }
+    let example = [
+        Frame::Data([1, 2, 3]),
+        Frame::Header {
+            id: 7,
+            bytes: [4, 5, 6],
+        },
+        Frame::Pair([0; 3], [-1, 1]),
+        Frame::Empty,
+    ];
+    let actual = json::to_string(&example[..]);
+    let expected = r#"[{"Data":[1,2,3]},{"Header":{"id":7,"bytes":[4,5,6]}},{"Pair":[[0,0,0],[-1,1]]},"Empty"]"#;
+    assert_eq!(actual, expected);
+    let actual = json::to_string(&Points::Line { ends: [1.5, 2.0] });
+    assert_eq!(actual, r#"{"type":"Line","ends":[1.5,2.0]}"#);
+}
+
+#[test]
+fn borrowed_fields() {
+    use std::borrow::Cow;
+
+    #[derive(Serialize_enum)]
+    enum Log<'a> {
+        Msg { text: &'a str },
+        Tag(Cow<'a, str>),
+        Pair(&'a str, u8),
+        Empty,
+    }
+    #[derive(Serialize_enum)]
+    #[serde(tag = "kind")]
+    enum Internal<'a, 'b: 'a, T> {
+        Msg { text: &'a str, value: &'b T },
+        Tag { tag: Cow<'b, str> },
+    }
+    let text = String::from("hello");
+    let example = [
+        Log::Msg { text: &text },
+        Log::Tag(Cow::Borrowed("borrowed")),
+        Log::Tag(Cow::Owned("owned".to_string())),
+        Log::Pair(&text[1..], 2),
+        Log::Empty,
+    ];
+    let actual = json::to_string(&example[..]);
+    let expected = r#"[{"Msg":{"text":"hello"}},{"Tag":"borrowed"},{"Tag":"owned"},{"Pair":["ello",2]},"Empty"]"#;
+    assert_eq!(actual, expected);
+    let example = [
+        Internal::Msg {
+            text: &text,
+            value: &1,
+        },
+        Internal::Tag {
+            tag: Cow::Borrowed("t"),
+        },
+    ];
+    let actual = json::to_string(&example[..]);
+    let expected = r#"[{"kind":"Msg","text":"hello","value":1},{"kind":"Tag","tag":"t"}]"#;
+    assert_eq!(actual, expected);
+}
+
+#[test]
+fn cfg_attributes() {
+    #[allow(dead_code)]
+    struct NotSerialize;
+    #[derive(Serialize_enum)]
+    #[serde(tag = "type")]
+    enum Connection {
+        Tcp {
+            #[cfg_attr(all(), serde(rename = "address"))]
+            addr: String,
+            #[cfg(any())]
+            proxy: NotSerialize,
+            #[cfg_attr(any(), serde(skip))]
+            port: u16,
+        },
+        #[cfg(any())]
+        Tls { config: NotSerialize },
+        #[cfg_attr(all(), serde(rename = "local"))]
+        Unix,
+    }
+    let example = [
+        Connection::Tcp {
+            addr: "::1".to_string(),
+            port: 80,
+        },
+        Connection::Unix,
+    ];
+    let actual = json::to_string(&example[..]);
+    let expected = r#"[{"type":"Tcp","address":"::1","port":80},{"type":"local"}]"#;
+    assert_eq!(actual, expected);
+}
+
+#[test]
+#[allow(non_camel_case_types)]
+fn raw_identifiers() {
+    #[derive(Serialize_enum)]
+    enum r#enum {
+        Node {
+            r#type: u8,
+            r#match: bool,
+            r#ref: &'static str,
+        },
+        r#struct(u8),
+        r#fn,
+    }
+    #[derive(Serialize_enum)]
+    #[serde(tag = "kind")]
+    enum Internal {
+        Node { r#type: u8, r#match: bool },
+    }
+    let example = [
+        r#enum::Node {
+            r#type: 1,
+            r#match: true,
+            r#ref: "r",
+        },
+        r#enum::r#struct(2),
+        r#enum::r#fn,
+    ];
+    let actual = json::to_string(&example[..]);
+    let expected = r#"[{"Node":{"type":1,"match":true,"ref":"r"}},{"struct":2},"fn"]"#;
+    assert_eq!(actual, expected);
+    let actual = json::to_string(&Internal::Node {
+        r#type: 1,
+        r#match: false,
+    });
+    assert_eq!(actual, r#"{"kind":"Node","type":1,"match":false}"#);
+}
+
+#[test]
+fn adversarial_names() {
+    use common::adversarial_names::{External, Internal};
+
+    let example = [
+        External::__Visitor {
+            state: 1,
+            data: "d".to_string(),
+            __out: 2,
+            __marker: "m".into(),
+            __state: "s".into(),
+            __seen: true,
+        },
+        External::__State(3, 4),
+        External::__Map,
+        External::__AsStruct,
+    ];
+    let actual = json::to_string(&example[..]);
+    let expected = r#"[{"__Visitor":{"state":1,"data":"d","__out":2,"__marker":"m","__state":"s","__seen":true}},{"__State":[3,4]},"__Map","__AsStruct"]"#;
+    assert_eq!(actual, expected);
+    let actual = json::to_string(&Internal::__Map {
+        state: 1,
+        data: 2,
+        __state: 3,
+    });
+    assert_eq!(actual, r#"{"type":"__Map","state":1,"data":2,"__state":3}"#);
+}
+
+#[test]
+fn local_names() {
+    use common::local_names::{External, Internal};
+
+    let actual = json::to_string(&[External::Key { k: 1 }, External::Tuple(2, 3)][..]);
+    assert_eq!(actual, r#"[{"Key":{"k":1}},{"Tuple":[2,3]}]"#);
+    let actual = json::to_string(&Internal::Hex { hex: 10 });
+    assert_eq!(actual, r#"{"type":"Hex","hex":"0a"}"#);
+}
+
+#[test]
+fn strict_lints() {
+    use common::strict::{Converted, External, Header, Internal, OrderingDef, Untagged, Wire};
+
+    let example = [
+        External::Named {
+            value: 1,
+            array: [2],
+            text: "a".into(),
+            list: vec![],
+            header: Header { id: 3 },
+            skipped: true,
+        },
+        External::Tuple(4, [5]),
+        External::Newtype(6),
+        External::Unit,
+    ];
+    let actual = json::to_string(&example[..]);
+    let expected = r#"[{"Named":{"value":1,"array":[2],"text":"a","id":3}},{"Tuple":[4,[5]]},{"Newtype":6},"Unit"]"#;
+    assert_eq!(actual, expected);
+    let example = Internal::Named {
+        x: 1,
+        header: Header { id: 2 },
+    };
+    assert_eq!(
+        json::to_string(&example),
+        r#"{"type":"Named","x":1,"id":2}"#
+    );
+    let example = [Untagged::Named { x: 1 }, Untagged::Tuple(2, 3)];
+    assert_eq!(json::to_string(&example[..]), r#"[{"x":1},[2,3]]"#);
+    let example = Wire::Named { camelCase: 1 };
+    assert_eq!(json::to_string(&example), r#"{"Named":{"camelCase":1}}"#);
+    assert_eq!(json::to_string(&Converted::Unit), r#"{"type":"Unit"}"#);
+    let fragment = OrderingDef::serialize(&std::cmp::Ordering::Less);
+    assert!(matches!(fragment, miniserde::ser::Fragment::Str(_)));
+}
+
+#[test]
+fn field_attributes() {
+    use common::field_attributes::{External, Internal, Untagged};
+
+    let fields = r#""y":1,"default":7,"skipped_deserializing":0,"id":"4""#;
+    let actual = json::to_string(&External::example());
+    assert_eq!(actual, format!(r#"{{"C":{{{}}}}}"#, fields));
+    let actual = json::to_string(&Internal::example());
+    assert_eq!(actual, format!(r#"{{"type":"C",{}}}"#, fields));
+    let actual = json::to_string(&Untagged::example());
+    assert_eq!(actual, format!(r#"{{{}}}"#, fields));
+}
+
+#[test]
+fn crate_path() {
+    use common::crate_path::Event;
+
+    let example = [Event::Start { at: 3 }, Event::Stop(4), Event::Reset];
+    let actual = json::to_string(&example[..]);
+    assert_eq!(actual, r#"[{"Start":{"at":3}},{"Stop":4},"Reset"]"#);
+}
+
+#[test]
+fn shadowed_prelude() {
+    use common::shadowed_prelude::{External, Internal};
+
+    let example = [
+        External::Unit,
+        External::Named { at: 1, count: 0 },
+        External::Newtype(2),
+        External::Tuple(3, 4),
+    ];
+    let actual = json::to_string(&example[..]);
+    assert_eq!(
+        actual,
+        r#"["Unit",{"Named":{"at":1,"count":0}},{"Newtype":2},{"Tuple":[3,4]}]"#
+    );
+    let actual = json::to_string(&[Internal::Unit, Internal::Named { at: 1 }][..]);
+    assert_eq!(actual, r#"[{"type":"Unit"},{"type":"Named","at":1}]"#);
+}
+
+#[test]
+fn public_api_only() {
+    use common::public_api_only::{External, Internal};
+
+    let example = [
+        External::A { x: 1, label: None },
+        External::B(2, "two".to_string()),
+        External::C(true),
+        External::D,
+    ];
+    let actual = json::to_string(&example[..]);
+    let expected = r#"[{"A":{"x":1,"label":null}},{"B":[2,"two"]},{"C":true},"D"]"#;
+    assert_eq!(actual, expected);
+    let actual = json::to_string(&[Internal::A { x: 1 }, Internal::B][..]);
+    assert_eq!(actual, r#"[{"type":"A","x":1},{"type":"B"}]"#);
+}